@@ -0,0 +1,145 @@
+//! A programmatic API for loading and running Roxfiles
+//!
+//! ```no_run
+//! let results = rox::Rox::from_path("roxfile.yml")?
+//!     .env("PROFILE", "release")
+//!     .run_task("build")?;
+//! results.check_results()?;
+//! # Ok::<(), rox::errors::RoxError>(())
+//! ```
+use crate::errors::RoxError;
+use crate::models::{FailurePolicy, JobResults, Pipeline, RoxFile, Task};
+use crate::modules::execution::environment::{apply_env_layers, load_env, EnvMap};
+use crate::modules::execution::reporter::{Reporter, Reporters};
+use crate::modules::execution::{execute_pipeline, execute_task};
+use std::collections::HashMap;
+
+/// The path reported for Roxfiles that aren't loaded from disk
+const STRING_SOURCE: &str = "<string>";
+
+/// A loaded Roxfile, ready to have its Tasks & Pipelines run
+///
+/// Results are logged just as they are by the CLI, but nothing is
+/// printed while running unless a reporter is attached.
+pub struct Rox {
+    roxfile: RoxFile,
+    file_path: String,
+    env_overrides: EnvMap,
+    parallel: bool,
+    keep_going: bool,
+    reporters: Reporters<'static>,
+}
+
+impl Rox {
+    fn new(roxfile: RoxFile, file_path: &str) -> Self {
+        Rox {
+            roxfile,
+            file_path: file_path.to_owned(),
+            env_overrides: EnvMap::new(),
+            parallel: false,
+            keep_going: false,
+            reporters: Reporters::new(),
+        }
+    }
+
+    /// Load a Roxfile, along with anything it includes
+    pub fn from_path(file_path: &str) -> Result<Self, RoxError> {
+        Ok(Rox::new(RoxFile::build(file_path)?, file_path))
+    }
+
+    /// Load a Roxfile from its contents
+    ///
+    /// Relative paths, including those of includes, are
+    /// resolved against the current working directory.
+    pub fn from_yaml(contents: &str) -> Result<Self, RoxError> {
+        Ok(Rox::new(
+            RoxFile::build_from_str(contents, STRING_SOURCE)?,
+            STRING_SOURCE,
+        ))
+    }
+
+    /// Set an environment variable for every Task, taking precedence over the Roxfile
+    pub fn env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env_overrides.insert(key.into(), value.into());
+        self
+    }
+
+    /// Run the Tasks within each Pipeline stage in parallel
+    pub fn parallel(mut self, parallel: bool) -> Self {
+        self.parallel = parallel;
+        self
+    }
+
+    /// Run every Pipeline stage even after a failure, like `--keep-going`
+    pub fn keep_going(mut self, keep_going: bool) -> Self {
+        self.keep_going = keep_going;
+        self
+    }
+
+    /// Attach a reporter, which is sent the events of every run
+    pub fn reporter(mut self, reporter: impl Reporter + 'static) -> Self {
+        self.reporters.add(reporter);
+        self
+    }
+
+    /// The loaded Roxfile, with Templates and matrices already expanded
+    pub fn roxfile(&self) -> &RoxFile {
+        &self.roxfile
+    }
+
+    /// Every Task, including hidden ones and the sub-Tasks of matrix Tasks
+    pub fn tasks(&self) -> &[Task] {
+        &self.roxfile.tasks
+    }
+
+    /// Every Pipeline
+    pub fn pipelines(&self) -> &[Pipeline] {
+        self.roxfile.pipelines.as_deref().unwrap_or_default()
+    }
+
+    /// Get the Tasks by name, with every level of environment applied
+    fn task_map(&self, layers: &[&EnvMap]) -> Result<HashMap<String, Task>, RoxError> {
+        let task_map = self
+            .roxfile
+            .tasks
+            .iter()
+            .map(|task| (task.name.to_owned(), task.clone()))
+            .collect();
+        apply_env_layers(task_map, layers, &self.env_overrides).map_err(RoxError::setup)
+    }
+
+    /// Run a Task, along with any Tasks that it depends on
+    pub fn run_task(&self, name: &str) -> Result<JobResults, RoxError> {
+        let roxfile_env =
+            load_env(&self.roxfile.env, &self.roxfile.env_file).map_err(RoxError::setup)?;
+        let task_map = self.task_map(&[&roxfile_env])?;
+        let task = task_map.get(name).ok_or_else(|| {
+            RoxError::invalid(format!("Task '{}' doesn't exist!", name), &self.file_path)
+        })?;
+        execute_task(task.clone(), &task_map, &self.reporters)
+    }
+
+    /// Run each stage of a Pipeline in order
+    pub fn run_pipeline(&self, name: &str) -> Result<JobResults, RoxError> {
+        let mut pipeline = self
+            .pipelines()
+            .iter()
+            .find(|pipeline| pipeline.name == name)
+            .cloned()
+            .ok_or_else(|| {
+                RoxError::invalid(
+                    format!("Pipeline '{}' doesn't exist!", name),
+                    &self.file_path,
+                )
+            })?;
+        if self.keep_going {
+            pipeline.on_failure = FailurePolicy::Continue;
+        }
+
+        let roxfile_env =
+            load_env(&self.roxfile.env, &self.roxfile.env_file).map_err(RoxError::setup)?;
+        let pipeline_env = load_env(&pipeline.env, &pipeline.env_file).map_err(RoxError::setup)?;
+        let task_map = self.task_map(&[&roxfile_env, &pipeline_env])?;
+        execute_pipeline(pipeline, &task_map, self.parallel, false, &self.reporters)
+    }
+}
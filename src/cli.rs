@@ -1,9 +1,20 @@
-use crate::models::{CiInfo, Docs, Pipeline, Task};
+use crate::models::{CiInfo, Docs, Pipeline, Task, Template};
+use crate::modules::completions::SHELLS;
+use crate::modules::execution::environment::parse_key_value;
+use crate::modules::execution::output::OutputFormat;
+use clap::builder::EnumValueParser;
 use clap::{crate_version, Arg, ArgAction, Command};
 
+/// Names that can't be used for Template params on the CLI,
+/// as they would clash with existing args.
+pub const RESERVED_PARAM_NAMES: [&str; 8] = [
+    "args", "env", "file", "help", "junit", "output", "set", "watch",
+];
+
 /// Dyanmically construct the CLI from the Roxfile
 pub fn construct_cli(
     tasks: &[Task],
+    templates: &Option<Vec<Template>>,
     pipelines: &Option<Vec<Pipeline>>,
     docs: &Option<Vec<Docs>>,
     ci: &Option<CiInfo>,
@@ -23,7 +34,7 @@ pub fn construct_cli(
     }
 
     // Tasks
-    let task_subcommands = build_task_subcommands(tasks);
+    let task_subcommands = build_task_subcommands(tasks, templates);
     cli = cli.subcommands(vec![task_subcommands]);
 
     // Pipelines
@@ -42,7 +53,6 @@ pub fn cli_builder(strict_subcommands: bool) -> Command {
         .version(crate_version!())
         .arg_required_else_help(true)
         .allow_external_subcommands(!strict_subcommands)
-        // TODO: Add a "watch" flag to run the command on file changes to a path?
         .arg(
             Arg::new("roxfile")
                 .long("file")
@@ -50,6 +60,68 @@ pub fn cli_builder(strict_subcommands: bool) -> Command {
                 .default_value("roxfile.yml")
                 .help("Path to a Roxfile"),
         )
+        .arg(
+            Arg::new("env")
+                .long("env")
+                .short('e')
+                .global(true)
+                .action(ArgAction::Append)
+                .value_name("KEY=VALUE")
+                .value_parser(parse_key_value)
+                .help("Set an environment variable for executed tasks, overriding the Roxfile."),
+        )
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .global(true)
+                .value_parser(EnumValueParser::<OutputFormat>::new())
+                .default_value("text")
+                .help("The format of Rox's output."),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Generate a shell completion script, which completes task, pipeline and docs names.")
+                .arg(
+                    Arg::new("shell")
+                        .required(true)
+                        .value_parser(SHELLS)
+                        .help("The shell to generate the script for."),
+                ),
+        )
+        .subcommand(
+            Command::new("__complete")
+                .hide(true)
+                .arg(
+                    Arg::new("index")
+                        .long("index")
+                        .required(true)
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    Arg::new("words")
+                        .num_args(0..)
+                        .last(true)
+                        .allow_hyphen_values(true),
+                ),
+        )
+        .subcommand(
+            Command::new("schema")
+                .about("Print the JSON Schema of the Roxfile, for use with YAML language servers."),
+        )
+        .subcommand(
+            Command::new("validate")
+                .about("Check the Roxfile for problems, reporting where each one is."),
+        )
+        .subcommand(
+            Command::new("pick").about(
+                "Pick a task or pipeline to run with a fuzzy finder, also shown when no subcommand is given.",
+            ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the tasks, pipelines, templates and docs in the Roxfile."),
+        )
         .subcommand(
             Command::new("logs")
                 .about("View logs for Rox invocations.")
@@ -59,6 +131,29 @@ pub fn cli_builder(strict_subcommands: bool) -> Command {
                         .required(false)
                         .value_parser(clap::value_parser!(i8))
                         .default_value("1"),
+                )
+                .arg(
+                    Arg::new("task")
+                        .long("task")
+                        .short('t')
+                        .required(false)
+                        .help("Show the captured output of a task instead of the results."),
+                )
+                .arg(
+                    Arg::new("page")
+                        .long("page")
+                        .required(false)
+                        .requires("task")
+                        .action(ArgAction::SetTrue)
+                        .help("Page through the captured output in a scrollable viewer."),
+                )
+                .arg(
+                    Arg::new("export")
+                        .long("export")
+                        .required(false)
+                        .conflicts_with("task")
+                        .value_parser(["junit"])
+                        .help("Print the results in another format, such as a JUnit XML report."),
                 ),
         )
 }
@@ -77,17 +172,64 @@ pub fn build_docs_subcommands(docs: &[Docs]) -> Command {
 }
 
 /// Build the `task` subcommand with individual tasks nested as subcommands
-pub fn build_task_subcommands(tasks: &[Task]) -> Command {
+///
+/// Tasks that use a Template with named params get an arg for each param.
+pub fn build_task_subcommands(tasks: &[Task], templates: &Option<Vec<Template>>) -> Command {
     let subcommands: Vec<Command> = tasks
         .iter()
         .filter(|target| !target.hide.unwrap_or_default())
-        .map(|task| Command::new(&task.name).about(task.description.to_owned().unwrap_or_default()))
+        .map(|task| {
+            let params = templates
+                .iter()
+                .flatten()
+                .find(|template| Some(&template.name) == task.uses.as_ref())
+                .and_then(|template| template.params.clone())
+                .unwrap_or_default();
+            let param_args: Vec<Arg> = params
+                .iter()
+                .filter(|param| !RESERVED_PARAM_NAMES.contains(&param.name.as_str()))
+                .map(|param| {
+                    let mut help = param.description.clone().unwrap_or_default();
+                    if let Some(default) = &param.default {
+                        help = format!("{} [template default: {}]", help, default);
+                    }
+                    Arg::new(&param.name)
+                        .long(&param.name)
+                        .required(false)
+                        .value_name("VALUE")
+                        .help(help.trim().to_owned())
+                })
+                .collect();
+
+            Command::new(&task.name)
+                .about(task.description.to_owned().unwrap_or_default())
+                .args(param_args)
+                .arg(
+                    Arg::new("args")
+                        .num_args(0..)
+                        .last(true)
+                        .allow_hyphen_values(true)
+                        .value_name("ARGS")
+                        .help("Arguments passed through to the task's command, after '--'."),
+                )
+        })
         .collect();
 
     Command::new("task")
         .about("Discrete executable tasks.")
         .long_about("Discrete units of execution containing a single runnable command.")
         .arg_required_else_help(true)
+        .arg(watch_arg())
+        .arg(junit_arg())
+        .arg(
+            Arg::new("set")
+                .long("set")
+                .global(true)
+                .action(ArgAction::Append)
+                .value_name("PARAM=VALUE")
+                .value_parser(parse_key_value)
+                .help("Override the value of a template param used by the task."),
+        )
         .subcommands(subcommands)
 }
 
@@ -113,5 +255,44 @@ pub fn build_pipeline_subcommands(pipelines: &[Pipeline]) -> Command {
                 .action(ArgAction::SetTrue)
                 .help("Run the pipeline's tasks in parallel."),
         )
+        .arg(
+            Arg::new("keep_going")
+                .long("keep-going")
+                .short('k')
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .help("Run the remaining stages after a failure, overriding the pipeline's 'on_failure'."),
+        )
+        .arg(
+            Arg::new("tui")
+                .long("tui")
+                .required(false)
+                .action(ArgAction::SetTrue)
+                .conflicts_with_all(["watch", "output"])
+                .help("Show a live dashboard of the pipeline's execution."),
+        )
+        .arg(watch_arg())
+        .arg(junit_arg())
         .subcommands(subcommands)
 }
+
+/// Flag to rerun a task or pipeline whenever its files change
+fn watch_arg() -> Arg {
+    Arg::new("watch")
+        .long("watch")
+        .short('w')
+        .required(false)
+        .global(true)
+        .action(ArgAction::SetTrue)
+        .help("Rerun on changes to the workdir or declared inputs of the task(s).")
+}
+
+/// Arg to write a JUnit XML report of a task or pipeline's results
+fn junit_arg() -> Arg {
+    Arg::new("junit")
+        .long("junit")
+        .required(false)
+        .global(true)
+        .value_name("PATH")
+        .help("Write a JUnit XML report of the results to the given path.")
+}
@@ -0,0 +1,212 @@
+//! The errors that can be returned by Rox
+use crate::models::JobResults;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt;
+
+/// A single problem within a Roxfile, along with where it was found
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub file_path: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+}
+
+impl Diagnostic {
+    /// Convert a YAML parsing error, which already knows its location
+    pub fn from_yaml_error(error: &serde_yaml::Error, file_path: &str) -> Self {
+        let message = error.to_string();
+        // The location is reported separately, so drop it from the message
+        let message = match error.location() {
+            Some(_) => message
+                .rsplit_once(" at line ")
+                .map_or(message.as_str(), |(message, _)| message)
+                .to_owned(),
+            None => message,
+        };
+        Diagnostic {
+            message,
+            file_path: file_path.to_owned(),
+            line: error.location().map(|location| location.line()),
+            column: error.location().map(|location| location.column()),
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.file_path)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+impl Error for Diagnostic {}
+
+/// Why an execution didn't succeed
+#[derive(Debug)]
+pub enum ExecutionError {
+    /// At least one Task failed, listed by name
+    Failed(Vec<String>),
+    /// The execution was cancelled, e.g. by Ctrl-C
+    Cancelled,
+    /// The Tasks couldn't be run at all, e.g. because an env file is missing
+    Setup(String),
+}
+
+impl ExecutionError {
+    /// Get the error describing the outcome of a job, if it didn't succeed
+    pub fn from_results(results: &JobResults) -> Option<Self> {
+        let cancelled = results
+            .results
+            .iter()
+            .any(|result| result.result == crate::models::PassFail::Cancelled);
+        let failed: Vec<String> = results
+            .results
+            .iter()
+            .filter(|result| result.is_failure())
+            .map(|result| result.name.to_owned())
+            .collect();
+        match (cancelled, failed.is_empty()) {
+            (true, _) => Some(ExecutionError::Cancelled),
+            (false, false) => Some(ExecutionError::Failed(failed)),
+            (false, true) => None,
+        }
+    }
+}
+
+/// Everything that can go wrong while using Rox
+#[derive(Debug)]
+pub enum RoxError {
+    /// The CLI args are invalid, or help was requested
+    Usage(clap::Error),
+    /// The Roxfile couldn't be read or parsed
+    Parse(Diagnostic),
+    /// The Roxfile, or how it's being used, is invalid
+    Validation(Vec<Diagnostic>),
+    /// The Tasks didn't all succeed
+    Execution(ExecutionError),
+    /// The logs couldn't be read or written
+    LogIo {
+        path: String,
+        source: std::io::Error,
+    },
+    /// Docs couldn't be displayed
+    Docs { name: String, message: String },
+    /// The CI results couldn't be retrieved
+    Ci(String),
+}
+
+impl RoxError {
+    /// Create a Validation error for a single problem without a known location
+    pub fn invalid(message: impl Into<String>, file_path: &str) -> Self {
+        RoxError::Validation(vec![Diagnostic {
+            message: message.into(),
+            file_path: file_path.to_owned(),
+            line: None,
+            column: None,
+        }])
+    }
+
+    pub fn log_io(path: impl Into<String>, source: std::io::Error) -> Self {
+        RoxError::LogIo {
+            path: path.into(),
+            source,
+        }
+    }
+
+    pub fn setup(message: impl fmt::Display) -> Self {
+        RoxError::Execution(ExecutionError::Setup(message.to_string()))
+    }
+}
+
+impl fmt::Display for RoxError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RoxError::Usage(e) => write!(f, "{}", e),
+            RoxError::Parse(diagnostic) => write!(f, "{}", diagnostic),
+            RoxError::Validation(diagnostics) => {
+                for diagnostic in diagnostics {
+                    writeln!(f, "{}", diagnostic)?;
+                }
+                write!(
+                    f,
+                    "> Found {} problem(s) in the Roxfile!",
+                    diagnostics.len()
+                )
+            }
+            RoxError::Execution(ExecutionError::Failed(tasks)) => {
+                write!(f, "> Failed task(s): {}", tasks.join(", "))
+            }
+            RoxError::Execution(ExecutionError::Cancelled) => {
+                write!(f, "> Execution was cancelled")
+            }
+            RoxError::Execution(ExecutionError::Setup(message)) => write!(f, "{}", message),
+            RoxError::LogIo { path, source } => {
+                write!(f, "Failed to access the logs at '{}': {}", path, source)
+            }
+            RoxError::Docs { name, message } => {
+                write!(f, "Failed to display the docs '{}': {}", name, message)
+            }
+            RoxError::Ci(message) => write!(f, "Failed to get the CI status: {}", message),
+        }
+    }
+}
+
+impl Error for RoxError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RoxError::Usage(e) => Some(e),
+            RoxError::LogIo { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<clap::Error> for RoxError {
+    fn from(e: clap::Error) -> Self {
+        RoxError::Usage(e)
+    }
+}
+
+#[test]
+fn from_results_prefers_cancellation() {
+    use crate::models::{PassFail, TaskResult};
+
+    let result = |name: &str, result: PassFail| TaskResult {
+        name: name.to_owned(),
+        command: String::new(),
+        stage: 1,
+        result,
+        elapsed_time: 0,
+        file_path: "roxfile.yml".to_owned(),
+        log_path: None,
+        attempts: 1,
+        allow_failure: false,
+        exit_code: None,
+        failed_line: None,
+    };
+    let mut results = JobResults {
+        job_name: "test".to_owned(),
+        execution_time: String::new(),
+        results: vec![result("pass", PassFail::Pass)],
+    };
+    assert!(ExecutionError::from_results(&results).is_none());
+
+    results.results.push(result("fail", PassFail::Fail));
+    assert!(matches!(
+        ExecutionError::from_results(&results),
+        Some(ExecutionError::Failed(tasks)) if tasks == vec!["fail"]
+    ));
+
+    results.results.push(result("cancel", PassFail::Cancelled));
+    assert!(matches!(
+        ExecutionError::from_results(&results),
+        Some(ExecutionError::Cancelled)
+    ));
+}
@@ -1,29 +1,152 @@
+mod api;
 mod cli;
+pub mod errors;
 pub mod models;
 mod modules;
 mod utils;
 
-use crate::cli::{cli_builder, construct_cli};
-use crate::modules::execution::{execute_pipeline, execute_task};
-use crate::modules::{ci, docs, logs};
+pub use crate::api::Rox;
+use crate::cli::{cli_builder, construct_cli, RESERVED_PARAM_NAMES};
+use crate::errors::RoxError;
+use crate::modules::execution::environment::{apply_env_layers, load_env, EnvMap};
+use crate::modules::execution::matrix::expand_matrix;
+use crate::modules::execution::model_injection::override_template_values;
+use crate::modules::execution::output::OutputFormat;
+pub use crate::modules::execution::reporter;
+use crate::modules::execution::reporter::{
+    ndjson_reporter, ConsoleReporter, JsonReporter, JunitReporter, Reporters,
+};
+use crate::modules::execution::{execute_pipeline, execute_task, processes};
+use crate::modules::includes::discover_roxfile;
+use crate::modules::{ci, completions, docs, list, logs, picker, schema, validation, watch};
+use clap::parser::ValueSource;
 use std::collections::HashMap;
-use std::error::Error;
 
-type RoxResult<T> = Result<T, Box<dyn Error>>;
-
-/// Get the filepath argument from the CLI
+/// Get the matches of the static parts of the CLI
 ///
 /// This is required because we might need to
-/// dynamically populate the CLI based on this arg
-fn get_filepath_arg_value() -> String {
+/// dynamically populate the CLI based on the filepath arg
+fn get_static_matches() -> Result<clap::ArgMatches, clap::Error> {
     let cli = cli_builder(false);
-    // Get the file arg from the CLI if set
-    let cli_matches = cli.clone().arg_required_else_help(false).get_matches();
-    cli_matches.get_one::<String>("roxfile").unwrap().to_owned()
+    cli.arg_required_else_help(false).try_get_matches()
+}
+
+/// Get the matches of the most deeply nested subcommand
+///
+/// Global args are propagated down, so these
+/// contain all of the values provided for them.
+fn get_leaf_matches(cli_matches: &clap::ArgMatches) -> &clap::ArgMatches {
+    let mut matches = cli_matches;
+    while let Some((_, sub_matches)) = matches.subcommand() {
+        matches = sub_matches;
+    }
+    matches
+}
+
+/// Get the environment variable overrides from the CLI
+fn get_env_arg_values(cli_matches: &clap::ArgMatches) -> EnvMap {
+    get_leaf_matches(cli_matches)
+        .get_many::<(String, String)>("env")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect()
+}
+
+/// Get the Template param overrides for a Task from the CLI
+///
+/// Values set via a param's own arg take precedence over `--set`.
+fn get_param_arg_values(
+    task_matches: &clap::ArgMatches,
+    template: &models::Template,
+) -> HashMap<String, String> {
+    let mut overrides: HashMap<String, String> = task_matches
+        .get_many::<(String, String)>("set")
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+    for param in template.params.iter().flatten() {
+        if RESERVED_PARAM_NAMES.contains(&param.name.as_str()) {
+            continue;
+        }
+        if let Some(value) = task_matches.get_one::<String>(&param.name) {
+            overrides.insert(param.name.to_owned(), value.to_owned());
+        }
+    }
+    overrides
+}
+
+/// Get the reporters for an execution from the CLI args
+///
+/// The dashboard shows the progress itself, so only the results are printed after it.
+fn build_reporters(
+    output: OutputFormat,
+    tui: bool,
+    junit_path: Option<&String>,
+) -> Reporters<'static> {
+    let mut reporters = Reporters::new();
+    match output {
+        OutputFormat::Text if tui => reporters.add(ConsoleReporter::summary()),
+        OutputFormat::Text => reporters.add(ConsoleReporter::new()),
+        OutputFormat::Json => reporters.add(JsonReporter),
+        OutputFormat::Ndjson => reporters.add(ndjson_reporter()),
+    }
+    if let Some(junit_path) = junit_path {
+        reporters.add(JunitReporter::new(junit_path));
+    }
+    reporters
+}
+
+/// Let the user pick a Task or Pipeline, returning the args that run it,
+/// or `None` if they cancel
+///
+/// Any args given alongside `pick`, such as `--env`, are kept.
+fn pick_args(roxfile: &models::RoxFile, file_path: &str) -> Result<Option<Vec<String>>, RoxError> {
+    if !picker::is_interactive() {
+        return Err(RoxError::setup(
+            "The picker requires an interactive terminal!",
+        ));
+    }
+    let entry = picker::pick(&picker::build_entries(roxfile, file_path))
+        .map_err(|e| RoxError::setup(format!("Failed to show the picker: {}", e)))?;
+    let Some(entry) = entry else {
+        return Ok(None);
+    };
+
+    let mut args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args.iter().skip(1).position(|arg| arg == "pick") {
+        args.remove(index + 1);
+    }
+    args.extend([entry.subcommand.to_owned(), entry.name]);
+    Ok(Some(args))
+}
+
+/// Handle an interrupt, e.g. Ctrl-C, returning whether to exit immediately
+///
+/// Tasks run in their own process groups, so the signal is forwarded to them.
+/// The first interrupt gives them a chance to exit cleanly so that the results
+/// can still be logged, while a second interrupt kills them immediately.
+/// Outside of executing Tasks, there's nothing to wait for.
+pub fn interrupt(signal: i32) -> bool {
+    if !processes::is_executing() || processes::is_watching() || processes::is_cancelled() {
+        processes::kill_all();
+        return true;
+    }
+    eprintln!("> Stopping running tasks, press Ctrl-C again to force...");
+    processes::cancel(signal);
+    false
+}
+
+/// Pass each SIGINT, SIGTERM or SIGHUP that's received to a function
+pub fn handle_signals(handler: impl Fn(i32) + Send + 'static) -> std::io::Result<()> {
+    processes::handle_signals(handler)
 }
 
 /// Entrypoint for the Crate CLI
-pub async fn rox() -> RoxResult<()> {
+///
+/// Nothing is printed for a returned error, which is left to the caller.
+pub async fn rox() -> Result<(), RoxError> {
     let start = std::time::Instant::now();
 
     // NOTE: Due to the dynamically generated nature of the CLI,
@@ -31,28 +154,117 @@ pub async fn rox() -> RoxResult<()> {
     // the filename arg and once to actually build the CLI.
 
     // Get the file arg from the CLI if set
-    let file_path = get_filepath_arg_value();
+    let static_matches = get_static_matches()?;
+    let file_path = static_matches
+        .get_one::<String>("roxfile")
+        .unwrap()
+        .to_owned();
+    // Without an explicit path, use the nearest Roxfile in a parent directory
+    let file_path = match static_matches.value_source("roxfile") {
+        Some(ValueSource::DefaultValue) => discover_roxfile(&file_path)
+            .map(|path| path.to_string_lossy().into_owned())
+            .unwrap_or(file_path),
+        _ => file_path,
+    };
+
+    // Completions have to work even when the Roxfile can't be built
+    match static_matches.subcommand() {
+        Some(("completions", completion_args)) => {
+            let shell = completion_args.get_one::<String>("shell").unwrap();
+            print!("{}", completions::completion_script(shell));
+            return Ok(());
+        }
+        Some(("__complete", complete_args)) => {
+            let words: Vec<String> = complete_args
+                .get_many::<String>("words")
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            let index = *complete_args.get_one::<usize>("index").unwrap();
+            let roxfile_path = completions::find_roxfile_arg(&words).unwrap_or(file_path);
+            let cli = match models::RoxFile::build(&roxfile_path) {
+                Ok(roxfile) => construct_cli(
+                    &roxfile.tasks,
+                    &roxfile.templates,
+                    &roxfile.pipelines,
+                    &roxfile.docs,
+                    &roxfile.ci,
+                ),
+                Err(_) => cli_builder(true),
+            };
+            for candidate in completions::complete(&cli, &words, index) {
+                println!("{}", candidate);
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    // The schema doesn't depend on any particular Roxfile, which might not exist
+    if let Some(("schema", _)) = static_matches.subcommand() {
+        schema::display_schema();
+        return Ok(());
+    }
+
+    // Validation has to happen before the Roxfile is built, as building fails on any problem
+    if let Some(("validate", validate_args)) = static_matches.subcommand() {
+        let output = *validate_args.get_one::<OutputFormat>("output").unwrap();
+        return validation::display_validation(&file_path, output);
+    }
     let roxfile = models::RoxFile::build(&file_path)?;
-    utils::print_horizontal_rule();
+    logs::set_log_root(&file_path);
 
     // Build & Generate the CLI based on the loaded Roxfile
     let cli = construct_cli(
         &roxfile.tasks,
+        &roxfile.templates,
         &roxfile.pipelines,
         &roxfile.docs,
         &roxfile.ci,
     );
-    let cli_matches = cli.get_matches();
+    let picking = match static_matches.subcommand_name() {
+        Some("pick") => true,
+        None => picker::is_interactive(),
+        _ => false,
+    };
+    let cli_matches = match picking {
+        true => match pick_args(&roxfile, &file_path)? {
+            Some(args) => cli.try_get_matches_from(args)?,
+            None => return Ok(()),
+        },
+        false => cli.try_get_matches()?,
+    };
+    let output = *get_leaf_matches(&cli_matches)
+        .get_one::<OutputFormat>("output")
+        .unwrap();
+    // Exported reports are printed on their own, so they can be redirected to a file
+    let exporting = matches!(
+        get_leaf_matches(&cli_matches).try_get_one::<String>("export"),
+        Ok(Some(_))
+    );
+    if output == OutputFormat::Text && !exporting {
+        utils::print_horizontal_rule();
+    }
+    let env_overrides = get_env_arg_values(&cli_matches);
+    let junit_path = get_leaf_matches(&cli_matches)
+        .try_get_one::<String>("junit")
+        .ok()
+        .flatten();
+    let roxfile_env = load_env(&roxfile.env, &roxfile.env_file).map_err(RoxError::setup)?;
 
     let task_map: HashMap<String, models::Task> = std::collections::HashMap::from_iter(
         roxfile
             .tasks
-            .into_iter()
-            .map(|task| (task.name.to_owned(), task)),
+            .iter()
+            .map(|task| (task.name.to_owned(), task.clone())),
     );
 
     let (_, args) = cli_matches.subcommand().unwrap();
     let subcommand_name = args.subcommand_name().unwrap_or("default");
+    let watching = args.subcommand().is_some_and(|(_, sub_args)| {
+        matches!(sub_args.try_get_one::<bool>("watch"), Ok(Some(true)))
+    });
 
     // Execute the Command
     match cli_matches.subcommand_name() {
@@ -63,38 +275,118 @@ pub async fn rox() -> RoxResult<()> {
                 .flatten()
                 .find(|doc| doc.name == subcommand_name)
                 .unwrap();
-            docs::display_docs(documentation);
-            std::process::exit(0);
+            docs::display_docs(documentation)?;
         }
         Some("logs") => {
             let number = args.get_one::<i8>("number").unwrap();
-            logs::display_logs(number);
-            std::process::exit(0);
+            match args.get_one::<String>("task") {
+                Some(task_name) => {
+                    logs::display_task_output(number, task_name, args.get_flag("page"), output)?
+                }
+                None if args.contains_id("export") => logs::export_junit(number)?,
+                None => logs::display_logs(number, output)?,
+            }
+        }
+        Some("list") => {
+            list::display_list(&roxfile, output);
         }
         Some("ci") => {
-            assert!(roxfile.ci.is_some());
-            ci::display_ci_status(roxfile.ci.unwrap()).await;
-            std::process::exit(0);
+            // Clap only adds the subcommand when CI is configured
+            ci::display_ci_status(roxfile.ci.unwrap()).await?;
         }
         Some("pl") => {
             let parallel = args.get_flag("parallel");
-            let pipeline = roxfile
+            let mut pipeline = roxfile
                 .pipelines
                 .into_iter()
                 .flatten()
                 .find(|pipeline| pipeline.name == subcommand_name)
                 .unwrap(); // Clap will catch a non-existent Pipeline for us
-            execute_pipeline(pipeline, &task_map, parallel);
+            if args.get_flag("keep_going") {
+                pipeline.on_failure = models::FailurePolicy::Continue;
+            }
+            let pipeline_env =
+                load_env(&pipeline.env, &pipeline.env_file).map_err(RoxError::setup)?;
+            let task_map =
+                apply_env_layers(task_map, &[&roxfile_env, &pipeline_env], &env_overrides)
+                    .map_err(RoxError::setup)?;
+            if watching {
+                let targets: Vec<String> = pipeline.stages.iter().flatten().cloned().collect();
+                let reporters = build_reporters(output, false, junit_path);
+                watch::watch(&targets, &task_map, || {
+                    let execution =
+                        execute_pipeline(pipeline.clone(), &task_map, parallel, false, &reporters);
+                    if let Err(e) = execution {
+                        eprintln!("{}", e);
+                    }
+                })
+                .map_err(RoxError::setup)?;
+            } else {
+                let tui = args.get_flag("tui");
+                let reporters = build_reporters(output, tui, junit_path);
+                let results = execute_pipeline(pipeline, &task_map, parallel, tui, &reporters)?;
+                results.check_results()?;
+            }
+        }
+        Some("task") => {
+            let mut task_map = apply_env_layers(task_map, &[&roxfile_env], &env_overrides)
+                .map_err(RoxError::setup)?;
+            let mut task = task_map.get(subcommand_name).unwrap().to_owned();
+            let (_, task_args) = args.subcommand().unwrap();
+            let template = roxfile
+                .templates
+                .iter()
+                .flatten()
+                .find(|template| Some(&template.name) == task.uses.as_ref());
+            match template {
+                Some(template) => {
+                    let overrides = get_param_arg_values(task_args, template);
+                    if !overrides.is_empty() {
+                        let task_file = task.file_path.clone().unwrap_or_default();
+                        task = override_template_values(task, template, overrides)
+                            .map_err(|e| RoxError::invalid(e.message, &task_file))?;
+                        task_map.insert(task.name.to_owned(), task.clone());
+                    }
+                }
+                None if task_args.contains_id("set") => {
+                    return Err(RoxError::invalid(
+                        format!("Task '{}' doesn't use a Template!", task.name),
+                        &task.file_path.unwrap_or_default(),
+                    ));
+                }
+                None => {}
+            }
+            if let Some(passed_args) = task_args.get_many::<String>("args") {
+                task.args = Some(passed_args.cloned().collect());
+                task_map.insert(task.name.to_owned(), task.clone());
+            }
+            // Regenerate the sub-Tasks of a matrix Task to pick up any changes
+            for sub_task in expand_matrix(&task) {
+                task_map.insert(sub_task.name.to_owned(), sub_task);
+            }
+            let reporters = build_reporters(output, false, junit_path);
+            if watching {
+                watch::watch(&[task.name.to_owned()], &task_map, || {
+                    if let Err(e) = execute_task(task.clone(), &task_map, &reporters) {
+                        eprintln!("{}", e);
+                    }
+                })
+                .map_err(RoxError::setup)?;
+            } else {
+                let results = execute_task(task, &task_map, &reporters)?;
+                results.check_results()?;
+            }
         }
-        Some("task") => execute_task(task_map.get(subcommand_name).unwrap().to_owned()),
         _ => unreachable!("Invalid subcommand"),
     };
 
-    println!(
-        "> Total elapsed time: {}s | {}ms",
-        start.elapsed().as_secs(),
-        start.elapsed().as_millis(),
-    );
+    if output == OutputFormat::Text && !exporting {
+        println!(
+            "> Total elapsed time: {}s | {}ms",
+            start.elapsed().as_secs(),
+            start.elapsed().as_millis(),
+        );
+    }
 
     Ok(())
 }
@@ -1,7 +1,30 @@
+use rox::errors::{ExecutionError, RoxError};
+
+/// Get the exit code for an error
+fn exit_code(error: &RoxError) -> i32 {
+    match error {
+        RoxError::Execution(ExecutionError::Failed(_)) => 2,
+        RoxError::Execution(ExecutionError::Cancelled) => 130,
+        _ => 1,
+    }
+}
+
 #[tokio::main]
 async fn main() {
-    if let Err(e) = rox::rox().await {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    if let Err(e) = rox::handle_signals(|signal| {
+        if rox::interrupt(signal) {
+            std::process::exit(128 + signal);
+        }
+    }) {
+        eprintln!("> Failed to set the signal handler: {}", e);
+    }
+
+    match rox::rox().await {
+        Ok(()) => {}
+        Err(RoxError::Usage(e)) => e.exit(),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(exit_code(&e));
+        }
     }
 }
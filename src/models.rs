@@ -1,25 +1,31 @@
 //! Contains the Structs for the Schema of the Roxfile
 //! as well as the validation logic.
-use crate::logs;
+use crate::errors::{ExecutionError, RoxError};
+use crate::modules::execution::matrix::expand_matrix_tasks;
 use crate::modules::execution::model_injection::{inject_task_metadata, inject_template_values};
-use crate::modules::execution::output;
-use crate::utils::{color_print, ColorEnum};
-use anyhow::Result;
+use crate::modules::includes::{load_roxfile, parse_roxfile};
+use crate::modules::validation::{to_diagnostic, validate_roxfile};
+use crate::utils::split_words;
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::error::Error;
 use std::fmt;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/// Schema for viewing CI pipeline results
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct CiInfo {
+    /// The CI provider to use, currently only `github_actions`
     pub provider: String,
+    /// The owner of the repository, e.g. a user or organization
     pub repo_owner: String,
     pub repo_name: String,
+    /// The name of the env var that contains an access token
     pub token_env_var: String,
 }
 
 /// Format for completed executions
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct JobResults {
     pub job_name: String,
     pub execution_time: String,
@@ -27,23 +33,12 @@ pub struct JobResults {
 }
 
 impl JobResults {
-    pub fn log_results(&self) {
-        let log_path = logs::write_logs(self);
-        println!("> Log file written to: {}", log_path);
-    }
-
-    pub fn display_results(&self) {
-        output::display_execution_results(self);
-    }
-
-    /// Triggers a non-zero exit if any job failed, otherwise passes.
-    pub fn check_results(&self) {
-        // TODO: Figure out a way to get this info without looping again
-        self.results.iter().for_each(|result| {
-            if result.result == PassFail::Fail {
-                std::process::exit(2)
-            }
-        });
+    /// Returns an error if any Task failed or the execution was cancelled
+    pub fn check_results(&self) -> Result<(), RoxError> {
+        match ExecutionError::from_results(self) {
+            Some(e) => Err(RoxError::Execution(e)),
+            None => Ok(()),
+        }
     }
 }
 
@@ -52,6 +47,19 @@ impl JobResults {
 pub enum PassFail {
     Pass,
     Fail,
+    Cached,
+    TimedOut,
+    NotRun,
+    Cancelled,
+}
+impl PassFail {
+    /// Whether the result should fail the execution
+    pub fn is_failure(&self) -> bool {
+        matches!(
+            self,
+            PassFail::Fail | PassFail::TimedOut | PassFail::Cancelled
+        )
+    }
 }
 impl std::fmt::Display for PassFail {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -59,20 +67,31 @@ impl std::fmt::Display for PassFail {
     }
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// How a piece of documentation is displayed
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 #[serde(rename_all = "lowercase")]
 pub enum DocsKind {
+    /// Opened in an in-terminal Markdown viewer
     Markdown,
+    /// Printed to the terminal
     Text,
+    /// Opened in a web browser
     URL,
 }
 
-#[derive(Deserialize, Debug, Clone)]
+/// Schema for Docs
+///
+/// Docs are files or links that can be viewed via the CLI.
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 pub struct Docs {
     pub name: String,
     pub description: Option<String>,
     pub kind: DocsKind,
+    /// A file path, or a URL for `url` Docs
     pub path: String,
+    /// The Roxfile that the Docs are defined in
+    #[serde(skip)]
+    pub file_path: Option<String>,
 }
 
 #[derive(PartialEq, Debug, Clone, Deserialize, Serialize)]
@@ -83,6 +102,27 @@ pub struct TaskResult {
     pub result: PassFail,
     pub elapsed_time: i64,
     pub file_path: String,
+    pub log_path: Option<String>,
+    #[serde(default = "default_attempts")]
+    pub attempts: u32,
+    #[serde(default)]
+    pub allow_failure: bool,
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    /// The line of the Task's script that failed, if the shell reported it
+    #[serde(default)]
+    pub failed_line: Option<u32>,
+}
+
+impl TaskResult {
+    /// Whether the result should fail the execution, ignoring allowed failures
+    pub fn is_failure(&self) -> bool {
+        self.result.is_failure() && !self.allow_failure
+    }
+}
+
+fn default_attempts() -> u32 {
+    1
 }
 
 // Create a custom Error type for Validation
@@ -109,83 +149,265 @@ pub trait Validate {
     fn validate(&self) -> Result<(), ValidationError>;
 }
 
+/// The shell or interpreter that runs a command, which is passed as its last arg
+///
+/// Either a command line such as `bash -euo pipefail -c`, or a list of the
+/// program & its args such as `["python3", "-c"]`. An empty list runs the
+/// command directly, splitting it into args without a shell.
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum Shell {
+    Line(String),
+    Argv(Vec<String>),
+}
+
+impl Shell {
+    /// Get the program & args that commands are appended to,
+    /// or `None` if a command line has an unclosed quote
+    pub fn argv(&self) -> Option<Vec<String>> {
+        match self {
+            Shell::Line(line) => split_words(line),
+            Shell::Argv(argv) => Some(argv.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Shell::Line(line) => write!(f, "{}", line),
+            Shell::Argv(argv) => write!(f, "{:?}", argv),
+        }
+    }
+}
+
 /// Schema for Tasks in the Roxfile
 ///
 /// Tasks are discrete units of execution
 /// that send commands to the shell.
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Task {
     pub name: String,
+    /// The shell command to run
     pub command: Option<String>,
+    /// A multi-line script to run with the Task's shell, instead of a command
+    pub script: Option<String>,
+    /// Defaults to the command
     pub description: Option<String>,
+    /// The Roxfile that the Task is defined in
+    #[serde(skip)]
     pub file_path: Option<String>,
+    /// The name of a Template to build the command from
     pub uses: Option<String>,
+    /// Values for the Template's 'symbols', in order
     pub values: Option<Vec<String>>,
+    /// Values for the Template's named 'params'
+    pub with: Option<HashMap<String, String>>,
+    /// Whether to hide the Task from the CLI's help
     pub hide: Option<bool>,
+    /// The directory to run the command in, defaulting to the Roxfile's directory
     pub workdir: Option<String>,
+    /// The shell that runs the command, defaulting to the Roxfile's `shell` or `sh -c`
+    pub shell: Option<Shell>,
+    /// Tasks that must be run before this one
+    pub depends_on: Option<Vec<String>>,
+    /// Environment variables for the command
+    pub env: Option<HashMap<String, String>>,
+    /// Files to load environment variables from
+    pub env_file: Option<Vec<String>>,
+    /// Glob patterns of the files that the Task reads, used for caching & watching
+    pub inputs: Option<Vec<String>>,
+    /// Glob patterns of the files that the Task creates, used for caching
+    pub outputs: Option<Vec<String>>,
+    /// Seconds to wait before the Task is killed
+    pub timeout: Option<u64>,
+    /// Number of times to retry the Task if it fails
+    pub retries: Option<u32>,
+    /// Seconds to wait before the first retry, doubling for each retry after
+    pub retry_delay: Option<u64>,
+    /// Whether the Task failing should be ignored
+    pub allow_failure: Option<bool>,
+    /// Named axes of values, with a sub-Task generated for each combination
+    pub matrix: Option<BTreeMap<String, Vec<String>>>,
+    /// Pass-through arguments supplied after `--` at invocation
+    #[serde(skip)]
+    pub args: Option<Vec<String>>,
 }
 
 impl Validate for Task {
     fn validate(&self) -> Result<(), ValidationError> {
-        let task_fail_message = format!("> Task '{}' failed validation!", self.name);
-
-        // Command and Uses cannot both be none
-        if self.command.is_none() & self.uses.is_none() {
-            color_print(vec![task_fail_message], ColorEnum::Red);
-            return Err(ValidationError {
-                message: "A Task must implement either 'command' or 'uses'!".to_owned(),
-            });
+        // Exactly one of Command, Script and Uses must be Some
+        let bodies = [
+            self.command.is_some(),
+            self.script.is_some(),
+            self.uses.is_some(),
+        ];
+        match bodies.iter().filter(|is_some| **is_some).count() {
+            0 => {
+                return Err(ValidationError {
+                    message: "A Task must implement one of 'command', 'script' or 'uses'!"
+                        .to_owned(),
+                })
+            }
+            1 => {}
+            _ => {
+                return Err(ValidationError {
+                    message: "A Task can only implement one of 'command', 'script' & 'uses'!"
+                        .to_owned(),
+                })
+            }
         }
 
-        // Command and Uses cannot both be Some
-        if self.uses.is_some() & self.command.is_some() {
-            color_print(vec![task_fail_message], ColorEnum::Red);
+        // If Uses is Some, either Values or With must also be Some
+        if self.uses.is_some() & self.values.is_none() & self.with.is_none() {
             return Err(ValidationError {
-                message: "A Task cannot implement both 'command' & 'uses'!".to_owned(),
+                message: "A Task that implements 'uses' must also implement 'values' or 'with'!"
+                    .to_owned(),
             });
         }
 
-        // If Uses is Some, Values must also be Some
-        if self.uses.is_some() & self.values.is_none() {
-            color_print(vec![task_fail_message], ColorEnum::Red);
+        // Values and With cannot both be Some
+        if self.values.is_some() & self.with.is_some() {
             return Err(ValidationError {
-                message: "A Task that implements 'uses' must also implement 'values'!".to_owned(),
+                message: "A Task cannot implement both 'values' & 'with'!".to_owned(),
             });
         }
 
         // If Uses is None, Values must also be None
         if self.uses.is_none() & self.values.is_some() {
-            color_print(vec![task_fail_message], ColorEnum::Red);
             return Err(ValidationError {
                 message: "A Task that implements 'values' must also implement 'uses'!".to_owned(),
             });
         }
 
+        // Every axis of the Matrix needs at least one value
+        if self
+            .matrix
+            .iter()
+            .flatten()
+            .any(|(_, values)| values.is_empty())
+        {
+            return Err(ValidationError {
+                message: "A Task's 'matrix' axes must each have at least one value!".to_owned(),
+            });
+        }
+
+        // If Uses is None, With must also be None
+        if self.uses.is_none() & self.with.is_some() {
+            return Err(ValidationError {
+                message: "A Task that implements 'with' must also implement 'uses'!".to_owned(),
+            });
+        }
+
         Ok(())
     }
 }
 
+/// Schema for Template parameters
+///
+/// Parameters are referenced by name within the
+/// Template's command, e.g. `{image_tag}`.
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TemplateParam {
+    pub name: String,
+    /// The value used when a Task doesn't set one
+    pub default: Option<String>,
+    pub description: Option<String>,
+}
+
+impl TemplateParam {
+    /// The placeholder used for this parameter within a command
+    pub fn placeholder(&self) -> String {
+        format!("{{{}}}", self.name)
+    }
+}
+
 /// Schema for Templates
 ///
 /// Templates are injectable commands that
 /// can be used by tasks.
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct Template {
     pub name: String,
+    /// The shell command, containing the symbols or params
     pub command: String,
+    /// Placeholders within the command, replaced positionally by a Task's 'values'
+    #[serde(default)]
     pub symbols: Vec<String>,
+    /// Named placeholders within the command, set by a Task's 'with'
+    pub params: Option<Vec<TemplateParam>>,
+    /// The Roxfile that the Template is defined in
+    #[serde(skip)]
+    pub file_path: Option<String>,
+}
+
+impl Template {
+    /// Check that a Task using this Template binds its values correctly
+    pub fn validate_bindings(&self, task: &Task) -> Result<(), ValidationError> {
+        let params = self.params.as_deref().unwrap_or_default();
+
+        if task.values.is_some() & !params.is_empty() {
+            return Err(ValidationError {
+                message: format!(
+                    "Template '{}' has named 'params', so they must be set with 'with'!",
+                    self.name
+                ),
+            });
+        }
+
+        for key in task.with.iter().flat_map(|with| with.keys()) {
+            if !params.iter().any(|param| &param.name == key) {
+                return Err(ValidationError {
+                    message: format!("Template '{}' has no param named '{}'!", self.name, key),
+                });
+            }
+        }
+
+        for param in params.iter().filter(|param| param.default.is_none()) {
+            let is_bound = task
+                .with
+                .as_ref()
+                .is_some_and(|with| with.contains_key(&param.name));
+            if !is_bound {
+                return Err(ValidationError {
+                    message: format!(
+                        "Template '{}' requires a value for its param '{}'!",
+                        self.name, param.name
+                    ),
+                });
+            }
+        }
+
+        Ok(())
+    }
 }
+
 impl Validate for Template {
     fn validate(&self) -> Result<(), ValidationError> {
-        let failure_message = format!("> Template '{}' failed validation!", self.name);
+        // Symbols and Params cannot both be used
+        if !self.symbols.is_empty() & self.params.is_some() {
+            return Err(ValidationError {
+                message: "A Template cannot implement both 'symbols' & 'params'!".to_owned(),
+            });
+        }
+
+        // All of the 'Params' must exist within the 'Command'
+        for param in self.params.iter().flatten() {
+            if !self.command.contains(&param.placeholder()) {
+                return Err(ValidationError {
+                    message: "A Template's 'params' must all exist within its 'command'!"
+                        .to_owned(),
+                });
+            }
+        }
 
         // All of the 'Symbol' items must exist within the 'Command'
         for symbol in &self.symbols {
             let exists = self.command.contains(symbol);
             if !exists {
-                color_print(vec![failure_message], ColorEnum::Red);
                 return Err(ValidationError {
                     message: "A Template's 'symbols' must all exist within its 'command'!"
                         .to_owned(),
@@ -200,38 +422,99 @@ impl Validate for Template {
 /// Schema for Pipelines
 ///
 /// Pipelines are collections of tasks.
-#[derive(Deserialize, Debug, Clone, Default)]
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
 #[serde(deny_unknown_fields)]
 pub struct Pipeline {
     pub name: String,
     pub description: Option<String>,
+    /// Stages of Task names, run in order
     pub stages: Vec<Vec<String>>,
+    /// Environment variables for every Task, overriding the Roxfile's
+    pub env: Option<HashMap<String, String>>,
+    /// Files to load environment variables from
+    pub env_file: Option<Vec<String>>,
+    /// What to do with the remaining stages after a Task fails
+    #[serde(default)]
+    pub on_failure: FailurePolicy,
+    /// The Roxfile that the Pipeline is defined in
+    #[serde(skip)]
+    pub file_path: Option<String>,
+}
+
+/// What to do with a Pipeline's remaining stages after a Task fails
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FailurePolicy {
+    /// Skip the remaining stages
+    #[default]
+    Stop,
+    /// Run the remaining stages anyway
+    Continue,
+}
+
+/// Another Roxfile to merge into this one
+///
+/// Either a path (or glob) relative to the including Roxfile,
+/// or a path along with a prefix to namespace its contents.
+#[derive(Deserialize, JsonSchema, Debug, Clone)]
+#[serde(untagged)]
+pub enum Include {
+    Path(String),
+    Namespaced {
+        path: String,
+        /// Prepended to every name in the included Roxfile, e.g. `prefix:name`
+        prefix: Option<String>,
+    },
 }
 
 /// The top-level structure of the Roxfile
-#[derive(Deserialize, Debug, Default, Clone)]
+#[derive(Deserialize, JsonSchema, Debug, Default, Clone)]
 #[serde(deny_unknown_fields)]
 pub struct RoxFile {
+    /// Other Roxfiles to merge into this one
+    pub include: Option<Vec<Include>>,
     pub ci: Option<CiInfo>,
     pub docs: Option<Vec<Docs>>,
+    #[serde(default)]
     pub tasks: Vec<Task>,
     pub pipelines: Option<Vec<Pipeline>>,
     pub templates: Option<Vec<Template>>,
+    /// Environment variables for every Task
+    pub env: Option<HashMap<String, String>>,
+    /// Files to load environment variables from
+    pub env_file: Option<Vec<String>>,
+    /// The shell for every Task that doesn't set its own, defaulting to `sh -c`
+    pub shell: Option<Shell>,
 }
 
 impl RoxFile {
-    /// Create a new instance of RoxFile from a file path and
-    /// run all additional validation and metadata injection.
-    pub fn build(file_path: &str) -> Result<Self> {
-        let file_string = std::fs::read_to_string(file_path)?;
-        let mut roxfile: RoxFile = serde_yaml::from_str(&file_string)?;
+    /// Create a new instance of RoxFile from a file path, merge in
+    /// any included Roxfiles and run all additional validation and
+    /// metadata injection.
+    pub fn build(file_path: &str) -> Result<Self, RoxError> {
+        let roxfile =
+            load_roxfile(file_path).map_err(|e| RoxError::Parse(to_diagnostic(e, file_path)))?;
+        Self::prepare(roxfile, file_path)
+    }
+
+    /// Build a Roxfile from its contents rather than reading it from disk.
+    ///
+    /// `file_path` is only used for relative paths and to report where problems were found.
+    pub fn build_from_str(contents: &str, file_path: &str) -> Result<Self, RoxError> {
+        let roxfile = parse_roxfile(contents, file_path)
+            .map_err(|e| RoxError::Parse(to_diagnostic(e, file_path)))?;
+        Self::prepare(roxfile, file_path)
+    }
+
+    /// Validate a freshly loaded Roxfile and expand its Tasks, ready to be run
+    fn prepare(mut roxfile: Self, file_path: &str) -> Result<Self, RoxError> {
+        // Validation
+        let diagnostics = validate_roxfile(&roxfile, file_path);
+        if !diagnostics.is_empty() {
+            return Err(RoxError::Validation(diagnostics));
+        }
 
         // Templates
-        let _ = roxfile
-            .templates
-            .iter()
-            .flatten()
-            .try_for_each(|template| template.validate());
         let template_map: HashMap<String, &Template> = std::collections::HashMap::from_iter(
             roxfile
                 .templates
@@ -242,18 +525,21 @@ impl RoxFile {
         );
 
         // Tasks
-        let _ = roxfile.tasks.iter().try_for_each(|task| task.validate());
         roxfile.tasks = inject_task_metadata(roxfile.tasks, file_path);
         roxfile.tasks = roxfile
             .tasks
             .into_iter()
             .map(|task| match task.uses.to_owned() {
-                Some(task_use) => {
-                    inject_template_values(task, template_map.get(&task_use).unwrap())
-                }
+                Some(task_use) => inject_template_values(task, template_map[&task_use]),
                 None => task,
             })
             .collect();
+        if let Some(shell) = &roxfile.shell {
+            for task in roxfile.tasks.iter_mut() {
+                task.shell.get_or_insert_with(|| shell.clone());
+            }
+        }
+        roxfile.tasks = expand_matrix_tasks(roxfile.tasks);
 
         Ok(roxfile)
     }
@@ -1,3 +1,4 @@
+use crate::errors::RoxError;
 use crate::models::CiInfo;
 use chrono::{DateTime, Utc};
 use cli_table::{format::Justify, print_stdout, Cell, Style, Table};
@@ -104,24 +105,36 @@ pub fn display_results_table(results: &[RunResult]) {
 }
 
 /// Show the most recent CI workflow
-pub async fn display_ci_status(ci_info: CiInfo) {
+pub async fn display_ci_status(ci_info: CiInfo) -> Result<(), RoxError> {
     // Configure Git and retrieve repo info
-    let repo = Repository::open_from_env().unwrap();
-    let head = repo.head().unwrap();
-    assert!(head.is_branch());
-    let branch = head.name().unwrap().split('/').last().unwrap();
+    let repo = Repository::open_from_env()
+        .map_err(|e| RoxError::Ci(format!("Failed to open the git repository: {}", e)))?;
+    let head = repo
+        .head()
+        .map_err(|e| RoxError::Ci(format!("Failed to get the current branch: {}", e)))?;
+    if !head.is_branch() {
+        return Err(RoxError::Ci("HEAD isn't on a branch!".to_owned()));
+    }
+    let branch = head
+        .shorthand()
+        .ok_or_else(|| RoxError::Ci("The branch name isn't valid UTF-8!".to_owned()))?;
     println!("> Getting CI status for branch: {}", branch);
 
     // Build an Authenticated GitHub Client
-    let token = std::env::var(ci_info.token_env_var).expect("Failed to get token from env var!");
+    let token = std::env::var(&ci_info.token_env_var).map_err(|_| {
+        RoxError::Ci(format!(
+            "Failed to get a token from the env var '{}'!",
+            ci_info.token_env_var
+        ))
+    })?;
     let octo_instance = octocrab::OctocrabBuilder::new()
         .personal_token(token)
         .build()
-        .unwrap();
+        .map_err(|e| RoxError::Ci(format!("Failed to build the GitHub client: {}", e)))?;
 
     // Verify that the client is authorized
     if octo_instance.current().user().await.is_err() {
-        panic!("GitHub client is not authorized!");
+        return Err(RoxError::Ci("GitHub client is not authorized!".to_owned()));
     }
 
     let workflow_instance = octo_instance.workflows(ci_info.repo_owner, ci_info.repo_name);
@@ -132,10 +145,10 @@ pub async fn display_ci_status(ci_info: CiInfo) {
         .branch(branch)
         .send()
         .await
-        .expect("Failed to retrieve workflow data!")
+        .map_err(|e| RoxError::Ci(format!("Failed to retrieve workflow data: {}", e)))?
         .into_iter()
         .next()
-        .expect("Failed to retrieve workflow!");
+        .ok_or_else(|| RoxError::Ci(format!("No workflow runs found for branch '{}'!", branch)))?;
 
     let results: Vec<RunResult> = workflow_instance
         .list_jobs(workflow.id)
@@ -144,7 +157,7 @@ pub async fn display_ci_status(ci_info: CiInfo) {
         .filter(Filter::All)
         .send()
         .await
-        .expect("Failed to retrieve CI results!")
+        .map_err(|e| RoxError::Ci(format!("Failed to retrieve CI results: {}", e)))?
         .into_iter()
         .flat_map(|job| {
             let results: Vec<RunResult> = job
@@ -153,10 +166,9 @@ pub async fn display_ci_status(ci_info: CiInfo) {
                 .map(|step| RunResult {
                     name: step.name,
                     job: job.name.clone(),
-                    status: if step.conclusion.is_none() {
-                        StepStatus::InProgress
-                    } else {
-                        step_conclusion_lookup(step.conclusion.as_ref().unwrap())
+                    status: match &step.conclusion {
+                        Some(conclusion) => step_conclusion_lookup(conclusion),
+                        None => StepStatus::InProgress,
                     },
                     started_at: step.started_at,
                     ended_at: step.completed_at,
@@ -167,4 +179,5 @@ pub async fn display_ci_status(ci_info: CiInfo) {
         .collect();
 
     display_results_table(&results);
+    Ok(())
 }
@@ -0,0 +1,199 @@
+//! Shell completions, which call back into Rox to complete the
+//! dynamically generated Task, Pipeline & Docs subcommands.
+use clap::{Arg, Command};
+
+/// The shells that completion scripts can be generated for
+pub const SHELLS: [&str; 5] = ["bash", "zsh", "fish", "powershell", "elvish"];
+
+// Bash splits words on ':' & '=', which appear in included and matrix Task names,
+// so the words are taken from the line instead and only the part of each candidate
+// after the last of those characters is replied with.
+const BASH: &str = r#"_rox() {
+    local cur words cword
+    if declare -F _get_comp_words_by_ref >/dev/null; then
+        _get_comp_words_by_ref -n =: cur words cword
+    else
+        read -ra words <<< "${COMP_LINE:0:COMP_POINT}"
+        [[ ${COMP_LINE:0:COMP_POINT} == *[[:space:]] ]] && words+=("")
+        cword=$((${#words[@]} - 1))
+        cur=${words[cword]}
+    fi
+    local IFS=$'\n'
+    COMPREPLY=($(rox __complete --index $((cword - 1)) -- "${words[@]:1}" 2>/dev/null))
+    local breaks=${COMP_WORDBREAKS//[^:=]/}
+    if [[ -n $breaks && $cur == *[$breaks]* ]]; then
+        local prefix=${cur%"${cur##*[$breaks]}"}
+        COMPREPLY=("${COMPREPLY[@]#"$prefix"}")
+    fi
+}
+complete -o default -F _rox rox
+"#;
+
+const ZSH: &str = r#"#compdef rox
+_rox() {
+    local -a candidates
+    candidates=(${(f)"$(rox __complete --index $((CURRENT - 2)) -- "${(@)words[2,-1]}" 2>/dev/null)"})
+    compadd -a candidates
+}
+compdef _rox rox
+"#;
+
+const FISH: &str = r#"function __rox_complete
+    set -l words (commandline -opc)[2..-1]
+    rox __complete --index (count $words) -- $words (commandline -ct) 2>/dev/null
+end
+complete -c rox -f -a '(__rox_complete)'
+"#;
+
+const POWERSHELL: &str = r#"Register-ArgumentCompleter -Native -CommandName rox -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements | Select-Object -Skip 1 | ForEach-Object { $_.ToString() })
+    $index = if ($wordToComplete) { $words.Count - 1 } else { $words.Count }
+    rox __complete --index $index -- @words 2>$null | ForEach-Object {
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }
+}
+"#;
+
+const ELVISH: &str = r#"set edit:completion:arg-completer[rox] = {|@words|
+    var index = (- (count $words) 2)
+    rox __complete --index $index -- $@words[1..] 2>/dev/null | from-lines
+}
+"#;
+
+/// Get the completion script for a shell
+pub fn completion_script(shell: &str) -> &'static str {
+    match shell {
+        "bash" => BASH,
+        "zsh" => ZSH,
+        "fish" => FISH,
+        "powershell" => POWERSHELL,
+        "elvish" => ELVISH,
+        _ => unreachable!("Clap only allows the supported shells"),
+    }
+}
+
+/// Get the Roxfile path from the words being completed, if one was given
+pub fn find_roxfile_arg(words: &[String]) -> Option<String> {
+    let options = words.iter().take_while(|word| *word != "--");
+    options
+        .enumerate()
+        .find_map(|(i, word)| match word.as_str() {
+            "-f" | "--file" => words.get(i + 1).cloned(),
+            _ => word
+                .strip_prefix("--file=")
+                .or_else(|| word.strip_prefix("-f").filter(|value| !value.is_empty()))
+                .map(str::to_owned),
+        })
+}
+
+/// Find the arg that an option word refers to, among those in scope
+fn find_option<'a>(args: &[&'a Arg], word: &str) -> Option<&'a Arg> {
+    let matches = |arg: &&&Arg| match word.strip_prefix("--") {
+        Some(long) => arg.get_long() == Some(long.split('=').next().unwrap_or_default()),
+        None => word.chars().nth(1).is_some() && arg.get_short() == word.chars().nth(1),
+    };
+    args.iter().find(matches).copied()
+}
+
+/// Whether an option word will consume the word after it as its value
+fn takes_next_value(arg: &Arg, word: &str) -> bool {
+    let inline_value = match word.strip_prefix("--") {
+        Some(long) => long.contains('='),
+        None => word.len() > 2,
+    };
+    arg.get_action().takes_values() && !inline_value
+}
+
+/// Get the candidates for the word at `index`, given the words before it.
+///
+/// The words are everything after the binary name, and the
+/// word being completed may be missing if it's still empty.
+pub fn complete(cli: &Command, words: &[String], index: usize) -> Vec<String> {
+    let current = words.get(index).map(String::as_str).unwrap_or_default();
+    let preceding = &words[..index.min(words.len())];
+
+    let mut command = cli;
+    let mut args: Vec<&Arg> = command.get_arguments().collect();
+    let mut value_of: Option<&Arg> = None;
+    for word in preceding {
+        if value_of.take().is_some() {
+            continue;
+        }
+        if word == "--" {
+            // Everything after this is passed through to the Task
+            return Vec::new();
+        }
+        if word.starts_with('-') && word.len() > 1 {
+            value_of = find_option(&args, word).filter(|arg| takes_next_value(arg, word));
+            continue;
+        }
+        if let Some(subcommand) = command.find_subcommand(word) {
+            command = subcommand;
+            args.extend(command.get_arguments());
+        }
+    }
+
+    let candidates: Vec<String> = match value_of {
+        Some(arg) => arg
+            .get_possible_values()
+            .iter()
+            .map(|value| value.get_name().to_owned())
+            .collect(),
+        None if current.starts_with('-') => args
+            .iter()
+            .filter(|arg| !arg.is_hide_set())
+            .filter_map(|arg| arg.get_long())
+            .map(|long| format!("--{}", long))
+            .collect(),
+        None => command
+            .get_subcommands()
+            .filter(|subcommand| !subcommand.is_hide_set())
+            .map(|subcommand| subcommand.get_name().to_owned())
+            .chain(
+                command
+                    .get_positionals()
+                    .flat_map(|arg| arg.get_possible_values())
+                    .map(|value| value.get_name().to_owned()),
+            )
+            .collect(),
+    };
+    candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(current))
+        .collect()
+}
+
+#[test]
+fn complete_walks_subcommands_and_options() {
+    let cli = Command::new("rox")
+        .arg(Arg::new("roxfile").long("file").short('f'))
+        .arg(
+            Arg::new("output")
+                .long("output")
+                .short('o')
+                .value_parser(["text", "json"]),
+        )
+        .subcommand(
+            Command::new("task")
+                .subcommand(Command::new("build"))
+                .subcommand(Command::new("bench"))
+                .subcommand(Command::new("secret").hide(true)),
+        )
+        .subcommand(Command::new("pl").subcommand(Command::new("ci")));
+    let words = |words: &[&str]| -> Vec<String> { words.iter().map(|w| w.to_string()).collect() };
+
+    assert_eq!(complete(&cli, &words(&[]), 0), vec!["task", "pl"]);
+    assert_eq!(
+        complete(&cli, &words(&["-f", "roxfile.yml", "task", "b"]), 3),
+        vec!["build", "bench"]
+    );
+    assert_eq!(complete(&cli, &words(&["task"]), 1), vec!["build", "bench"]);
+    assert_eq!(complete(&cli, &words(&["pl", "-o", "j"]), 2), vec!["json"]);
+    assert_eq!(complete(&cli, &words(&["pl", "--o"]), 1), vec!["--output"]);
+    assert!(complete(&cli, &words(&["task", "build", "--", "b"]), 3).is_empty());
+    assert_eq!(
+        find_roxfile_arg(&words(&["--file=other.yml", "task"])),
+        Some("other.yml".to_owned())
+    );
+}
@@ -1,3 +1,4 @@
+use crate::errors::RoxError;
 use crate::models::{Docs, DocsKind};
 use std::io::{stdout, Write};
 use termimad::crossterm::{
@@ -26,26 +27,32 @@ static KEYBINDINGS: &str = r#"
 ------
 "#;
 
-pub fn display_docs(docs: &Docs) {
+pub fn display_docs(docs: &Docs) -> Result<(), RoxError> {
+    let docs_error = |message: String| RoxError::Docs {
+        name: docs.name.to_owned(),
+        message,
+    };
+    let read_docs = || {
+        std::fs::read_to_string(&docs.path)
+            .map_err(|e| docs_error(format!("Failed to read '{}': {}", docs.path, e)))
+    };
+
     match docs.kind {
         DocsKind::Markdown => {
-            let markdown = std::fs::read_to_string(&docs.path).unwrap();
-            run_app(&markdown).unwrap();
-        }
-        DocsKind::Text => {
-            let contents = std::fs::read_to_string(&docs.path).unwrap();
-            println!("{}", contents);
+            run_app(&read_docs()?).map_err(|e| docs_error(e.to_string()))?;
         }
+        DocsKind::Text => println!("{}", read_docs()?),
         DocsKind::URL => {
             println!("> Opening '{}' in your browser...", docs.path);
-            webbrowser::open(&docs.path).unwrap()
+            webbrowser::open(&docs.path).map_err(|e| docs_error(e.to_string()))?;
         }
     }
+    Ok(())
 }
 
 /// Build and Run the terminal application
 /// Taken from -> https://github.com/Canop/termimad/blob/main/examples/scrollable/main.rs
-fn run_app(docs: &str) -> Result<(), Error> {
+pub fn run_app(docs: &str) -> Result<(), Error> {
     let mut w = stdout(); // we could also have used stderr
     let skin = make_skin();
     queue!(w, EnterAlternateScreen)?;
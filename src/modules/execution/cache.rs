@@ -0,0 +1,165 @@
+//! Fingerprinting of Task inputs to allow for skipping unchanged Tasks
+use crate::models::Task;
+use crate::modules::logs::log_dir;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+fn cache_dir() -> PathBuf {
+    log_dir().join("cache")
+}
+
+/// Resolve a path relative to the Task's working directory
+fn resolve_path(task: &Task, path: &str) -> PathBuf {
+    let workdir = task.workdir.clone().unwrap_or(".".to_string());
+    Path::new(&workdir).join(path)
+}
+
+/// The file is named after a hash of the Task's name, as
+/// sanitizing it could give different Tasks the same file
+fn fingerprint_path(task: &Task) -> PathBuf {
+    let name_hash = Sha256::digest(task.name.as_bytes());
+    cache_dir().join(format!("{:x}.fingerprint", name_hash))
+}
+
+/// Add a field to the hash, prefixed by its length so that
+/// the boundaries between fields can't be shifted around
+fn hash_field(hasher: &mut Sha256, field: impl AsRef<[u8]>) {
+    let field = field.as_ref();
+    hasher.update((field.len() as u64).to_le_bytes());
+    hasher.update(field);
+}
+
+/// Hash everything that affects the Task's run: its effective command,
+/// workdir, shell, env and pass-through args along with the paths and
+/// contents of its inputs.
+///
+/// Returns `None` if the Task doesn't declare any inputs.
+pub fn compute_fingerprint(task: &Task) -> Option<String> {
+    let inputs = task.inputs.as_ref()?;
+    let mut hasher = Sha256::new();
+    hash_field(&mut hasher, super::build_command(task));
+    hash_field(&mut hasher, task.workdir.clone().unwrap_or_default());
+    hash_field(
+        &mut hasher,
+        task.shell
+            .as_ref()
+            .map(ToString::to_string)
+            .unwrap_or_default(),
+    );
+
+    let mut env: Vec<(&String, &String)> = task.env.iter().flatten().collect();
+    env.sort();
+    hasher.update((env.len() as u64).to_le_bytes());
+    for (key, value) in env {
+        hash_field(&mut hasher, key);
+        hash_field(&mut hasher, value);
+    }
+    let args = task.args.as_deref().unwrap_or_default();
+    hasher.update((args.len() as u64).to_le_bytes());
+    for arg in args {
+        hash_field(&mut hasher, arg);
+    }
+
+    let mut files: Vec<PathBuf> = inputs
+        .iter()
+        .flat_map(|pattern| {
+            let pattern = resolve_path(task, pattern);
+            glob::glob(&pattern.to_string_lossy())
+                .into_iter()
+                .flatten()
+                .flatten()
+        })
+        .filter(|path| path.is_file())
+        .collect();
+    files.sort();
+    files.dedup();
+
+    for file in files {
+        hash_field(&mut hasher, file.to_string_lossy().as_bytes());
+        hash_field(&mut hasher, std::fs::read(&file).unwrap_or_default());
+    }
+
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// A Task is up-to-date if its fingerprint matches the one stored
+/// after its last successful run and all of its outputs exist.
+pub fn is_up_to_date(task: &Task, fingerprint: &str) -> bool {
+    let stored = std::fs::read_to_string(fingerprint_path(task)).unwrap_or_default();
+    let outputs_exist = task
+        .outputs
+        .iter()
+        .flatten()
+        .all(|output| resolve_path(task, output).exists());
+
+    stored == fingerprint && outputs_exist
+}
+
+/// Store the fingerprint of a successful run
+pub fn store_fingerprint(task: &Task, fingerprint: &str) -> std::io::Result<()> {
+    std::fs::create_dir_all(cache_dir())?;
+    std::fs::write(fingerprint_path(task), fingerprint)
+}
+
+#[test]
+fn fingerprint_changes_with_command() {
+    let mut task = Task {
+        name: "test".to_owned(),
+        command: Some("echo one".to_owned()),
+        inputs: Some(vec!["Cargo.toml".to_owned()]),
+        ..Default::default()
+    };
+    let first = compute_fingerprint(&task).unwrap();
+    assert_eq!(first, compute_fingerprint(&task).unwrap());
+
+    task.command = Some("echo two".to_owned());
+    let second = compute_fingerprint(&task).unwrap();
+    assert_ne!(first, second);
+
+    task.env = Some(std::collections::HashMap::from([(
+        "V".to_owned(),
+        "one".to_owned(),
+    )]));
+    let with_env = compute_fingerprint(&task).unwrap();
+    assert_ne!(second, with_env);
+    task.env = Some(std::collections::HashMap::from([(
+        "V".to_owned(),
+        "two".to_owned(),
+    )]));
+    assert_ne!(with_env, compute_fingerprint(&task).unwrap());
+
+    task.args = Some(vec!["a".to_owned(), "b".to_owned()]);
+    let with_args = compute_fingerprint(&task).unwrap();
+    task.args = Some(vec!["ab".to_owned()]);
+    assert_ne!(with_args, compute_fingerprint(&task).unwrap());
+
+    task.inputs = None;
+    assert!(compute_fingerprint(&task).is_none());
+}
+
+#[test]
+fn fingerprint_fields_are_separated() {
+    let task = |command: &str, workdir: &str| Task {
+        name: "test".to_owned(),
+        command: Some(command.to_owned()),
+        workdir: Some(workdir.to_owned()),
+        inputs: Some(vec![]),
+        ..Default::default()
+    };
+    assert_ne!(
+        compute_fingerprint(&task("echo ab", "c")),
+        compute_fingerprint(&task("echo a", "bc"))
+    );
+}
+
+#[test]
+fn fingerprint_paths_are_unique_per_task() {
+    let task = |name: &str| Task {
+        name: name.to_owned(),
+        ..Default::default()
+    };
+    assert_ne!(
+        fingerprint_path(&task("x:y")),
+        fingerprint_path(&task("x_y"))
+    );
+}
@@ -0,0 +1,391 @@
+//! Live terminal dashboard for Pipeline executions
+use super::matrix::expand_task_names;
+use super::reporter::{EventReporter, ExecutionEvent, Reporter, Reporters};
+use super::{execute_stages, processes};
+use crate::models::{PassFail, Pipeline, Task, TaskResult};
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::{Frame, Terminal};
+use std::collections::HashMap;
+use std::io::stdout;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::time::{Duration, Instant};
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const TICK_RATE: Duration = Duration::from_millis(100);
+
+static KEYBINDINGS: &str =
+    " q/Esc: close | k/Up, j/Down: select task | u/PgUp, d/PgDn: scroll output | f: follow output ";
+
+enum TaskStatus {
+    Pending,
+    Running,
+    Finished(PassFail),
+}
+
+struct TaskRow {
+    name: String,
+    stage: i8,
+    status: TaskStatus,
+    started_at: Option<Instant>,
+    elapsed: Option<Duration>,
+    output: Vec<String>,
+}
+
+impl TaskRow {
+    fn new(name: &str, stage: i8) -> Self {
+        TaskRow {
+            name: name.to_owned(),
+            stage,
+            status: TaskStatus::Pending,
+            started_at: None,
+            elapsed: None,
+            output: Vec::new(),
+        }
+    }
+
+    fn elapsed_seconds(&self) -> String {
+        match (self.elapsed, self.started_at) {
+            (Some(elapsed), _) => format!("{:.1}", elapsed.as_secs_f32()),
+            (None, Some(started_at)) => format!("{:.1}", started_at.elapsed().as_secs_f32()),
+            (None, None) => "-".to_owned(),
+        }
+    }
+}
+
+/// State of the dashboard between renders
+struct Dashboard {
+    title: String,
+    rows: Vec<TaskRow>,
+    table_state: TableState,
+    // None means the output pane follows the most recent line
+    scroll: Option<u16>,
+    tick: usize,
+    finished: bool,
+}
+
+impl Dashboard {
+    fn new(pipeline: &Pipeline, task_map: &HashMap<String, Task>) -> Self {
+        let rows: Vec<TaskRow> = pipeline
+            .stages
+            .iter()
+            .enumerate()
+            .flat_map(|(stage_number, stage)| {
+                expand_task_names(stage, task_map)
+                    .into_iter()
+                    .map(move |name| TaskRow::new(&name, stage_number as i8 + 1))
+            })
+            .collect();
+
+        Dashboard {
+            title: pipeline.name.to_owned(),
+            rows,
+            table_state: TableState::default().with_selected(Some(0)),
+            scroll: None,
+            tick: 0,
+            finished: false,
+        }
+    }
+
+    /// Find the first row for a Task in the given state, if any
+    fn find_row(&mut self, name: &str, stage: i8, running: bool) -> Option<&mut TaskRow> {
+        self.rows.iter_mut().find(|row| {
+            row.name == name
+                && row.stage == stage
+                && match row.status {
+                    TaskStatus::Pending => !running,
+                    TaskStatus::Running => running,
+                    TaskStatus::Finished(_) => false,
+                }
+        })
+    }
+
+    fn handle_event(&mut self, event: ExecutionEvent) {
+        match event {
+            ExecutionEvent::Started { name, stage, .. } => {
+                if self.find_row(&name, stage, false).is_none() {
+                    // Prerequisites aren't listed in the Pipeline's stages,
+                    // so add them to the end of the stage they ran in
+                    let position = self
+                        .rows
+                        .iter()
+                        .position(|row| row.stage > stage)
+                        .unwrap_or(self.rows.len());
+                    self.rows.insert(position, TaskRow::new(&name, stage));
+                }
+                let row = self.find_row(&name, stage, false).unwrap();
+                row.status = TaskStatus::Running;
+                row.started_at = Some(Instant::now());
+            }
+            ExecutionEvent::Output {
+                name, stage, line, ..
+            } => {
+                if let Some(row) = self.find_row(&name, stage, true) {
+                    row.output.push(line);
+                }
+            }
+            ExecutionEvent::Finished(result) => {
+                // Tasks that were cached or weren't run never started
+                let running = !matches!(result.result, PassFail::NotRun | PassFail::Cached);
+                if let Some(row) = self.find_row(&result.name, result.stage, running) {
+                    row.status = TaskStatus::Finished(result.result);
+                    row.elapsed = row.started_at.map(|started_at| started_at.elapsed());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn select(&mut self, offset: isize) {
+        let selected = self.table_state.selected().unwrap_or(0) as isize;
+        let max_index = self.rows.len().saturating_sub(1) as isize;
+        self.table_state
+            .select(Some((selected + offset).clamp(0, max_index) as usize));
+        self.scroll = None;
+    }
+
+    fn scroll_output(&mut self, offset: i32, pane_height: u16) {
+        let line_count = self
+            .table_state
+            .selected()
+            .and_then(|index| self.rows.get(index))
+            .map_or(0, |row| row.output.len());
+        let bottom = line_count.saturating_sub(pane_height as usize) as i32;
+        let current = self.scroll.map_or(bottom, |scroll| scroll as i32);
+        let target = (current + offset).clamp(0, bottom);
+
+        self.scroll = match target == bottom {
+            true => None,
+            false => Some(target as u16),
+        };
+    }
+}
+
+/// Execute the Pipeline in the background while rendering its progress
+///
+/// Events are also sent to the given reporter, alongside the dashboard.
+pub fn run_dashboard(
+    pipeline: &Pipeline,
+    task_map: &HashMap<String, Task>,
+    parallel: bool,
+    reporter: &dyn Reporter,
+) -> Vec<Vec<TaskResult>> {
+    let (sender, receiver) = mpsc::channel();
+    let mut dashboard = Dashboard::new(pipeline, task_map);
+    // Tasks can't read from the terminal while the dashboard is drawn on it
+    let _terminal = processes::claim_terminal();
+
+    std::thread::scope(|scope| {
+        let execution = scope.spawn(move || {
+            // The dashboard finishes once the sender is dropped along with the reporters
+            let reporters = Reporters::new()
+                .with(EventReporter::new(move |event| {
+                    let _ = sender.send(event);
+                }))
+                .with(reporter);
+            execute_stages(
+                &pipeline.stages,
+                task_map,
+                parallel,
+                &pipeline.on_failure,
+                &reporters,
+            )
+        });
+
+        if let Err(e) = run_app(&mut dashboard, receiver) {
+            eprintln!("> Failed to render the dashboard: {}", e);
+        }
+        if !dashboard.finished {
+            println!("> Dashboard closed, waiting for the pipeline to finish...");
+        }
+        execution.join().unwrap()
+    })
+}
+
+/// Draw the dashboard until the user closes it
+fn run_app(dashboard: &mut Dashboard, receiver: Receiver<ExecutionEvent>) -> std::io::Result<()> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+    terminal.hide_cursor()?;
+
+    let result = (|| -> std::io::Result<()> {
+        loop {
+            loop {
+                match receiver.try_recv() {
+                    Ok(event) => dashboard.handle_event(event),
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        dashboard.finished = true;
+                        break;
+                    }
+                }
+            }
+
+            let mut pane_height = 0;
+            terminal.draw(|frame| pane_height = render(frame, dashboard))?;
+            dashboard.tick += 1;
+
+            if !event::poll(TICK_RATE)? {
+                continue;
+            }
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => break,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        // Raw mode swallows the signal, so stop the Tasks directly
+                        if !dashboard.finished {
+                            processes::cancel(libc::SIGINT);
+                        }
+                        break;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => dashboard.select(-1),
+                    KeyCode::Down | KeyCode::Char('j') => dashboard.select(1),
+                    KeyCode::PageUp | KeyCode::Char('u') => {
+                        dashboard.scroll_output(-(pane_height as i32), pane_height)
+                    }
+                    KeyCode::PageDown | KeyCode::Char('d') => {
+                        dashboard.scroll_output(pane_height as i32, pane_height)
+                    }
+                    KeyCode::Char('f') => dashboard.scroll = None,
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    })();
+
+    disable_raw_mode()?;
+    execute!(stdout(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+/// Render a single frame, returning the height of the output pane
+fn render(frame: &mut Frame, dashboard: &mut Dashboard) -> u16 {
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(3), Constraint::Length(1)])
+        .split(frame.size());
+    let panes = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+        .split(sections[0]);
+
+    let spinner = SPINNER[dashboard.tick % SPINNER.len()];
+    let rows: Vec<Row> = dashboard
+        .rows
+        .iter()
+        .map(|row| {
+            let (status, color) = match &row.status {
+                TaskStatus::Pending => ("Pending".to_owned(), Color::DarkGray),
+                TaskStatus::Running => (format!("{} Running", spinner), Color::Yellow),
+                TaskStatus::Finished(PassFail::Pass) => ("Pass".to_owned(), Color::Green),
+                TaskStatus::Finished(PassFail::Cached) => ("Cached".to_owned(), Color::Cyan),
+                TaskStatus::Finished(PassFail::NotRun) => ("NotRun".to_owned(), Color::DarkGray),
+                TaskStatus::Finished(PassFail::Cancelled) => {
+                    ("Cancelled".to_owned(), Color::Magenta)
+                }
+                TaskStatus::Finished(result) => (result.to_string(), Color::Red),
+            };
+            Row::new(vec![
+                Cell::from(row.stage.to_string()),
+                Cell::from(row.name.to_owned()),
+                Cell::from(status).style(Style::default().fg(color)),
+                Cell::from(row.elapsed_seconds()),
+            ])
+        })
+        .collect();
+
+    let status = match dashboard.finished {
+        true => "Finished",
+        false => "Running",
+    };
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(5),
+            Constraint::Min(10),
+            Constraint::Length(11),
+            Constraint::Length(8),
+        ],
+    )
+    .header(
+        Row::new(vec!["Stage", "Task", "Status", "Time (s)"])
+            .style(Style::default().fg(Color::Yellow)),
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" {} | {} ", dashboard.title, status)),
+    )
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, panes[0], &mut dashboard.table_state);
+
+    let selected = dashboard
+        .table_state
+        .selected()
+        .and_then(|index| dashboard.rows.get(index));
+    let pane_height = panes[1].height.saturating_sub(2);
+    let (title, lines): (String, Vec<Line>) = match selected {
+        Some(row) => (
+            format!(" Output: {} ", row.name),
+            row.output
+                .iter()
+                .map(|line| Line::from(line.as_str()))
+                .collect(),
+        ),
+        None => (" Output ".to_owned(), Vec::new()),
+    };
+    let bottom = lines.len().saturating_sub(pane_height as usize) as u16;
+    let output = Paragraph::new(lines)
+        .scroll((dashboard.scroll.unwrap_or(bottom), 0))
+        .block(Block::default().borders(Borders::ALL).title(title));
+    frame.render_widget(output, panes[1]);
+
+    frame.render_widget(
+        Paragraph::new(KEYBINDINGS).style(Style::default().fg(Color::DarkGray)),
+        sections[1],
+    );
+    pane_height
+}
+
+#[test]
+fn dashboard_tracks_task_events() {
+    use super::reporter::Stream;
+
+    let pipeline = Pipeline {
+        name: "test".to_owned(),
+        stages: vec![vec!["build".to_owned()], vec!["test".to_owned()]],
+        ..Default::default()
+    };
+    let mut dashboard = Dashboard::new(&pipeline, &HashMap::new());
+
+    // Prerequisites are inserted into the stage they ran in
+    dashboard.handle_event(ExecutionEvent::Started {
+        name: "setup".to_owned(),
+        stage: 1,
+        command: "echo done".to_owned(),
+    });
+    dashboard.handle_event(ExecutionEvent::Output {
+        name: "setup".to_owned(),
+        stage: 1,
+        stream: Stream::Stdout,
+        line: "done".to_owned(),
+    });
+    let names: Vec<&str> = dashboard.rows.iter().map(|row| row.name.as_str()).collect();
+    assert_eq!(names, vec!["build", "setup", "test"]);
+    assert_eq!(dashboard.rows[1].output, vec!["done".to_owned()]);
+    assert!(matches!(dashboard.rows[1].status, TaskStatus::Running));
+}
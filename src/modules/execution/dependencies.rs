@@ -0,0 +1,225 @@
+//! Resolution of Task dependencies declared via `depends_on`
+use crate::errors::RoxError;
+use crate::models::Task;
+use std::collections::{HashMap, HashSet};
+
+/// Make sure that the given Tasks and all of their prerequisites exist,
+/// so that running them can't fail part way through.
+///
+/// A missing Task is reported against the Roxfile that refers to it.
+pub fn check_tasks_exist(
+    names: &[String],
+    file_path: &str,
+    task_map: &HashMap<String, Task>,
+) -> Result<(), RoxError> {
+    let mut stack: Vec<(&String, &str)> = names.iter().map(|name| (name, file_path)).collect();
+    let mut checked: HashSet<&String> = HashSet::new();
+    while let Some((name, file_path)) = stack.pop() {
+        if !checked.insert(name) {
+            continue;
+        }
+        let Some(task) = task_map.get(name) else {
+            let message = format!("Task '{}' doesn't exist!", name);
+            return Err(RoxError::invalid(message, file_path));
+        };
+        let task_file_path = task.file_path.as_deref().unwrap_or(file_path);
+        stack.extend(
+            task.depends_on
+                .iter()
+                .flatten()
+                .map(|dependency| (dependency, task_file_path)),
+        );
+    }
+    Ok(())
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum VisitState {
+    InProgress,
+    Done,
+}
+
+/// Find a cycle within the dependency graph, if there is one,
+/// as the path of Task names that leads back to its start.
+///
+/// Dependencies that don't exist are ignored.
+pub fn find_dependency_cycle(tasks: &[Task]) -> Option<Vec<String>> {
+    let task_map: HashMap<&str, &Task> =
+        HashMap::from_iter(tasks.iter().map(|task| (task.name.as_str(), task)));
+
+    let mut states: HashMap<&str, VisitState> = HashMap::new();
+    tasks.iter().find_map(|task| {
+        let mut path = Vec::new();
+        find_cycle(&task.name, &task_map, &mut states, &mut path)
+    })
+}
+
+/// Depth-first search that returns the offending path if a cycle is found
+fn find_cycle<'a>(
+    name: &'a str,
+    task_map: &HashMap<&'a str, &'a Task>,
+    states: &mut HashMap<&'a str, VisitState>,
+    path: &mut Vec<&'a str>,
+) -> Option<Vec<String>> {
+    match states.get(name) {
+        Some(VisitState::Done) => return None,
+        Some(VisitState::InProgress) => {
+            let start = path.iter().position(|step| *step == name).unwrap();
+            let mut cycle: Vec<String> = path[start..].iter().map(|s| s.to_string()).collect();
+            cycle.push(name.to_owned());
+            return Some(cycle);
+        }
+        None => {}
+    }
+
+    let task = task_map.get(name)?;
+    states.insert(name, VisitState::InProgress);
+    path.push(name);
+    for dependency in task.depends_on.iter().flatten() {
+        if let Some(cycle) = find_cycle(dependency, task_map, states, path) {
+            return Some(cycle);
+        }
+    }
+    path.pop();
+    states.insert(name, VisitState::Done);
+    None
+}
+
+/// Resolve a set of target Tasks into layers of execution.
+///
+/// Every prerequisite is scheduled exactly once, in a layer before
+/// any Task that depends on it. Tasks within a single layer don't
+/// depend on each other and can be run in parallel. Prerequisites that
+/// are already in `completed` are not scheduled again, while the targets
+/// themselves are always scheduled as many times as they're listed.
+pub fn resolve_execution_layers(
+    targets: &[String],
+    task_map: &HashMap<String, Task>,
+    completed: &HashSet<String>,
+) -> Vec<Vec<String>> {
+    let target_set: HashSet<&String> = HashSet::from_iter(targets);
+    let mut depths: HashMap<String, usize> = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+
+    for target in targets {
+        compute_depth(
+            target,
+            task_map,
+            completed,
+            &target_set,
+            &mut depths,
+            &mut order,
+        );
+    }
+
+    let layer_count = depths.values().max().map_or(0, |depth| depth + 1);
+    let mut layers: Vec<Vec<String>> = vec![Vec::new(); layer_count];
+    for name in order {
+        let occurrences = if target_set.contains(&name) {
+            targets.iter().filter(|target| **target == name).count()
+        } else {
+            1
+        };
+        let layer = &mut layers[depths[&name]];
+        layer.extend(std::iter::repeat_n(name, occurrences));
+    }
+    layers
+}
+
+/// Calculate how many layers of prerequisites must run before a Task
+fn compute_depth(
+    name: &String,
+    task_map: &HashMap<String, Task>,
+    completed: &HashSet<String>,
+    targets: &HashSet<&String>,
+    depths: &mut HashMap<String, usize>,
+    order: &mut Vec<String>,
+) -> usize {
+    if let Some(depth) = depths.get(name) {
+        return *depth;
+    }
+
+    let depends_on = task_map.get(name).and_then(|task| task.depends_on.as_ref());
+    let depth = depends_on
+        .into_iter()
+        .flatten()
+        .filter(|dependency| targets.contains(dependency) || !completed.contains(*dependency))
+        .map(|dependency| {
+            compute_depth(dependency, task_map, completed, targets, depths, order) + 1
+        })
+        .max()
+        .unwrap_or(0);
+
+    depths.insert(name.to_owned(), depth);
+    order.push(name.to_owned());
+    depth
+}
+
+#[test]
+fn resolve_execution_layers_orders_prerequisites() {
+    let task = |name: &str, depends_on: &[&str]| Task {
+        name: name.to_owned(),
+        command: Some("true".to_owned()),
+        depends_on: Some(depends_on.iter().map(|dep| dep.to_string()).collect()),
+        ..Default::default()
+    };
+    let task_map = HashMap::from_iter(
+        [
+            task("build", &[]),
+            task("lint", &[]),
+            task("test", &["build"]),
+            task("release", &["test", "lint", "build"]),
+        ]
+        .into_iter()
+        .map(|task| (task.name.to_owned(), task)),
+    );
+
+    let layers = resolve_execution_layers(&["release".to_owned()], &task_map, &HashSet::new());
+    assert_eq!(
+        layers,
+        vec![
+            vec!["build".to_owned(), "lint".to_owned()],
+            vec!["test".to_owned()],
+            vec!["release".to_owned()],
+        ]
+    );
+
+    let completed = HashSet::from(["build".to_owned()]);
+    let layers = resolve_execution_layers(&["test".to_owned()], &task_map, &completed);
+    assert_eq!(layers, vec![vec!["test".to_owned()]]);
+}
+
+#[test]
+fn find_dependency_cycle_detects_cycles() {
+    let task = |name: &str, depends_on: &str| Task {
+        name: name.to_owned(),
+        command: Some("true".to_owned()),
+        depends_on: Some(vec![depends_on.to_owned()]),
+        ..Default::default()
+    };
+    let cycle = find_dependency_cycle(&[task("a", "b"), task("b", "a")]);
+    assert_eq!(cycle.unwrap(), vec!["a", "b", "a"]);
+    assert!(find_dependency_cycle(&[task("a", "b"), task("b", "c")]).is_none());
+}
+
+#[test]
+fn check_tasks_exist_finds_missing_prerequisites() {
+    let task = |name: &str, depends_on: &[&str]| Task {
+        name: name.to_owned(),
+        depends_on: Some(depends_on.iter().map(|name| name.to_string()).collect()),
+        file_path: Some("roxfile.yml".to_owned()),
+        ..Default::default()
+    };
+    let mut task_map = HashMap::from([
+        ("a".to_owned(), task("a", &["b"])),
+        ("b".to_owned(), task("b", &[])),
+    ]);
+    assert!(check_tasks_exist(&["a".to_owned()], "roxfile.yml", &task_map).is_ok());
+
+    task_map.insert("b".to_owned(), task("b", &["missing"]));
+    let error = check_tasks_exist(&["a".to_owned()], "roxfile.yml", &task_map).unwrap_err();
+    assert!(matches!(
+        error,
+        RoxError::Validation(diagnostics) if diagnostics[0].message == "Task 'missing' doesn't exist!"
+    ));
+}
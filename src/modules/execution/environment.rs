@@ -0,0 +1,139 @@
+//! Resolution of the environment variables passed to Tasks
+use crate::models::{Task, ValidationError};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+
+pub type EnvMap = HashMap<String, String>;
+
+/// Parse the contents of a dotenv file into a map of variables
+pub fn parse_env_file(contents: &str) -> Result<EnvMap, ValidationError> {
+    let mut env = EnvMap::new();
+
+    for (line_number, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+
+        let (key, value) = line.split_once('=').ok_or_else(|| ValidationError {
+            message: format!("Invalid line {} in env file: '{}'", line_number + 1, line),
+        })?;
+        env.insert(key.trim().to_owned(), parse_env_value(value.trim()));
+    }
+
+    Ok(env)
+}
+
+/// Strip quotes from a dotenv value, or trailing comments if it's unquoted
+fn parse_env_value(value: &str) -> String {
+    if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        return value[1..value.len() - 1]
+            .replace("\\n", "\n")
+            .replace("\\\"", "\"")
+            .replace("\\\\", "\\");
+    }
+    if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+        return value[1..value.len() - 1].to_owned();
+    }
+    match value.split_once(" #") {
+        Some((value, _)) => value.trim_end().to_owned(),
+        None => value.to_owned(),
+    }
+}
+
+/// Parse a `KEY=VALUE` pair supplied on the command line
+pub fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err(format!("'{}' is not in the format KEY=VALUE", arg)),
+    }
+}
+
+/// Load the variables for a single level of configuration.
+///
+/// Env files are applied in order, followed by the `env` map,
+/// so explicitly set values take precedence.
+pub fn load_env(env: &Option<EnvMap>, env_files: &Option<Vec<String>>) -> Result<EnvMap> {
+    let mut resolved = EnvMap::new();
+
+    for env_file in env_files.iter().flatten() {
+        let contents = std::fs::read_to_string(env_file)
+            .with_context(|| format!("Failed to read env file '{}'", env_file))?;
+        resolved.extend(parse_env_file(&contents)?);
+    }
+    resolved.extend(env.clone().unwrap_or_default());
+
+    Ok(resolved)
+}
+
+/// Resolve the final environment for every Task.
+///
+/// Layers are applied from lowest to highest precedence, followed by
+/// the Task's own variables and finally any command line overrides.
+pub fn apply_env_layers(
+    task_map: HashMap<String, Task>,
+    layers: &[&EnvMap],
+    overrides: &EnvMap,
+) -> Result<HashMap<String, Task>> {
+    task_map
+        .into_iter()
+        .map(|(name, mut task)| {
+            let mut env = EnvMap::new();
+            for layer in layers {
+                env.extend(layer.iter().map(|(k, v)| (k.to_owned(), v.to_owned())));
+            }
+            env.extend(load_env(&task.env, &task.env_file)?);
+            env.extend(overrides.iter().map(|(k, v)| (k.to_owned(), v.to_owned())));
+
+            task.env = Some(env);
+            task.env_file = None;
+            Ok((name, task))
+        })
+        .collect()
+}
+
+#[test]
+fn parse_env_file_valid() {
+    let contents = r#"
+# A comment
+FOO=bar
+export QUOTED="hello world"
+SINGLE='single # not a comment'
+TRAILING=value # a comment
+"#;
+    let env = parse_env_file(contents).unwrap();
+    assert_eq!(env.get("FOO").unwrap(), "bar");
+    assert_eq!(env.get("QUOTED").unwrap(), "hello world");
+    assert_eq!(env.get("SINGLE").unwrap(), "single # not a comment");
+    assert_eq!(env.get("TRAILING").unwrap(), "value");
+}
+
+#[test]
+fn apply_env_layers_precedence() {
+    let task = Task {
+        name: "test".to_owned(),
+        env: Some(EnvMap::from([
+            ("TASK".to_owned(), "task".to_owned()),
+            ("OVERRIDDEN".to_owned(), "task".to_owned()),
+        ])),
+        ..Default::default()
+    };
+    let roxfile_env = EnvMap::from([
+        ("ROXFILE".to_owned(), "roxfile".to_owned()),
+        ("TASK".to_owned(), "roxfile".to_owned()),
+    ]);
+    let pipeline_env = EnvMap::from([("ROXFILE".to_owned(), "pipeline".to_owned())]);
+    let overrides = EnvMap::from([("OVERRIDDEN".to_owned(), "cli".to_owned())]);
+
+    let task_map = apply_env_layers(
+        HashMap::from([(task.name.to_owned(), task)]),
+        &[&roxfile_env, &pipeline_env],
+        &overrides,
+    )
+    .unwrap();
+    let env = task_map["test"].env.as_ref().unwrap();
+    assert_eq!(env["ROXFILE"], "pipeline");
+    assert_eq!(env["TASK"], "task");
+    assert_eq!(env["OVERRIDDEN"], "cli");
+}
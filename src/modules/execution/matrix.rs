@@ -0,0 +1,126 @@
+//! Expansion of matrix Tasks into a sub-Task for each combination of values
+use crate::models::Task;
+use std::collections::{BTreeMap, HashMap};
+
+/// Get every combination of the matrix's values, one from each axis
+fn combinations(matrix: &BTreeMap<String, Vec<String>>) -> Vec<Vec<(&String, &String)>> {
+    matrix
+        .iter()
+        .fold(vec![Vec::new()], |combinations, (axis, values)| {
+            combinations
+                .iter()
+                .flat_map(|combination| {
+                    values.iter().map(move |value| {
+                        let mut combination = combination.clone();
+                        combination.push((axis, value));
+                        combination
+                    })
+                })
+                .collect()
+        })
+}
+
+/// Replace each `{matrix.<axis>}` placeholder with the combination's value
+fn substitute(text: &str, combination: &[(&String, &String)]) -> String {
+    combination
+        .iter()
+        .fold(text.to_owned(), |text, (axis, value)| {
+            text.replace(&format!("{{matrix.{}}}", axis), value)
+        })
+}
+
+/// Generate the sub-Tasks of a matrix Task, e.g. `build[target=x86_64]`
+pub fn expand_matrix(task: &Task) -> Vec<Task> {
+    let Some(matrix) = &task.matrix else {
+        return Vec::new();
+    };
+
+    combinations(matrix)
+        .into_iter()
+        .map(|combination| {
+            let label = combination
+                .iter()
+                .map(|(axis, value)| format!("{}={}", axis, value))
+                .collect::<Vec<String>>()
+                .join(",");
+            let expand =
+                |text: &Option<String>| text.as_ref().map(|text| substitute(text, &combination));
+
+            Task {
+                name: format!("{}[{}]", task.name, label),
+                command: expand(&task.command),
+                script: expand(&task.script),
+                description: expand(&task.description),
+                workdir: expand(&task.workdir),
+                env: task.env.as_ref().map(|env| {
+                    env.iter()
+                        .map(|(key, value)| (key.to_owned(), substitute(value, &combination)))
+                        .collect()
+                }),
+                matrix: None,
+                ..task.clone()
+            }
+        })
+        .collect()
+}
+
+/// Add the generated sub-Tasks of each matrix Task directly after it
+pub fn expand_matrix_tasks(tasks: Vec<Task>) -> Vec<Task> {
+    tasks
+        .into_iter()
+        .flat_map(|task| {
+            let sub_tasks = expand_matrix(&task);
+            std::iter::once(task).chain(sub_tasks)
+        })
+        .collect()
+}
+
+/// Replace the names of any matrix Tasks with the names of their sub-Tasks
+pub fn expand_task_names(names: &[String], task_map: &HashMap<String, Task>) -> Vec<String> {
+    names
+        .iter()
+        .flat_map(|name| match task_map.get(name) {
+            Some(task) if task.matrix.is_some() => expand_matrix(task)
+                .into_iter()
+                .map(|sub_task| sub_task.name)
+                .collect(),
+            _ => vec![name.to_owned()],
+        })
+        .collect()
+}
+
+#[test]
+fn matrix_expands_cartesian_product() {
+    let task = Task {
+        name: "build".to_owned(),
+        command: Some("cargo build -p {matrix.crate} --target {matrix.target}".to_owned()),
+        matrix: Some(BTreeMap::from([
+            (
+                "target".to_owned(),
+                vec!["x86_64".to_owned(), "aarch64".to_owned()],
+            ),
+            (
+                "crate".to_owned(),
+                vec!["cli".to_owned(), "core".to_owned()],
+            ),
+        ])),
+        ..Default::default()
+    };
+    let sub_tasks = expand_matrix(&task);
+
+    let names: Vec<&str> = sub_tasks.iter().map(|task| task.name.as_str()).collect();
+    assert_eq!(
+        names,
+        vec![
+            "build[crate=cli,target=x86_64]",
+            "build[crate=cli,target=aarch64]",
+            "build[crate=core,target=x86_64]",
+            "build[crate=core,target=aarch64]",
+        ]
+    );
+    assert_eq!(
+        sub_tasks[3].command,
+        Some("cargo build -p core --target aarch64".to_owned())
+    );
+    assert!(sub_tasks.iter().all(|task| task.matrix.is_none()));
+}
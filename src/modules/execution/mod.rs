@@ -1,9 +1,30 @@
+pub mod cache;
+pub mod dashboard;
+pub mod dependencies;
+pub mod environment;
+pub mod matrix;
 pub mod model_injection;
 pub mod output;
-use crate::models::{JobResults, PassFail, Pipeline, Task, TaskResult};
+pub mod processes;
+pub mod reporter;
+pub mod script;
+use crate::errors::RoxError;
+use crate::logs;
+use crate::models::{FailurePolicy, JobResults, PassFail, Pipeline, Task, TaskResult};
+use crate::utils::{shell_quote, split_words};
+use dependencies::{check_tasks_exist, resolve_execution_layers};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::collections::HashMap;
-use std::process::{Command, ExitStatus};
+use reporter::{Reporter, Stream};
+use script::ScriptFile;
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Child, Command, ExitStatus, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::time::Duration;
 
 pub fn get_result_passfail(result: Result<ExitStatus, std::io::Error>) -> PassFail {
     // If the command doesn't exist, we get an error here
@@ -11,34 +32,361 @@ pub fn get_result_passfail(result: Result<ExitStatus, std::io::Error>) -> PassFa
         return PassFail::Fail;
     }
 
-    if result.unwrap().code().unwrap() == 0 {
+    if result.unwrap().success() {
         return PassFail::Pass;
     }
 
     PassFail::Fail
 }
 
+/// The placeholder that pass-through arguments are substituted into
+const ARGS_PLACEHOLDER: &str = "{args}";
+
+/// Build the command that will actually be run for a Task.
+///
+/// Pass-through arguments are substituted into the `{args}` placeholder
+/// if the command contains one, otherwise they are appended. A script
+/// is returned as-is, as it's passed the arguments when it's run.
+pub fn build_command(task: &Task) -> String {
+    if let Some(script) = &task.script {
+        return script.trim_end().to_owned();
+    }
+    let command = task.command.clone().unwrap_or_default();
+    let args = task
+        .args
+        .iter()
+        .flatten()
+        .map(|arg| shell_quote(arg))
+        .collect::<Vec<String>>()
+        .join(" ");
+
+    if command.contains(ARGS_PLACEHOLDER) {
+        command.replace(ARGS_PLACEHOLDER, &args)
+    } else if args.is_empty() {
+        command
+    } else {
+        format!("{} {}", command, args)
+    }
+}
+
+/// The shell used when neither the Task nor the Roxfile sets one
+const DEFAULT_SHELL: [&str; 2] = ["sh", "-c"];
+
+/// Get the program & args of the Task's shell, which are empty if it runs without one
+pub fn shell_argv(task: &Task) -> Option<Vec<String>> {
+    match &task.shell {
+        Some(shell) => shell.argv(),
+        None => Some(DEFAULT_SHELL.map(str::to_owned).to_vec()),
+    }
+}
+
+/// Build the program & args that run a command, via the Task's shell if it has one.
+///
+/// Without a shell, the command is split into args itself.
+/// Returns `None` if a quote is left unclosed.
+pub fn build_argv(task: &Task, command: &str) -> Option<Vec<String>> {
+    let mut argv = shell_argv(task)?;
+    if argv.is_empty() {
+        return split_words(command);
+    }
+    argv.push(command.to_owned());
+    Some(argv)
+}
+
 /// Run a Task
-pub fn run_task(task: &Task, stage_number: i8) -> TaskResult {
+///
+/// Progress and output are sent to the reporter. Tasks whose inputs
+/// haven't changed since their last successful run are skipped.
+///
+/// Failed attempts are retried up to the Task's `retries`, waiting
+/// `retry_delay` seconds before the first retry and doubling after each.
+pub fn run_task(task: &Task, stage_number: i8, reporter: &dyn Reporter) -> TaskResult {
     let start = std::time::Instant::now();
+    let command = build_command(task);
 
-    let workdir = task.workdir.clone().unwrap_or(".".to_string());
-    let command = task.command.as_ref().unwrap();
-
-    println!("> Running command: '{}'", command);
-    let command_results = Command::new("sh")
-        .current_dir(workdir)
-        .arg("-c")
-        .arg(command)
-        .status();
+    let fingerprint = cache::compute_fingerprint(task);
+    let (attempt, log_path, attempts) = match &fingerprint {
+        Some(fingerprint) if cache::is_up_to_date(task, fingerprint) => {
+            (Attempt::new(PassFail::Cached), None, 0)
+        }
+        _ => {
+            reporter.task_started(&task.name, stage_number + 1, &command);
+            match logs::create_output_file(&task.name) {
+                Ok((log_path, log_file)) => {
+                    let (attempt, attempts) =
+                        run_attempts(task, &command, &log_file, stage_number, reporter);
+                    if let (Some(fingerprint), PassFail::Pass) = (&fingerprint, &attempt.result) {
+                        if let Err(e) = cache::store_fingerprint(task, fingerprint) {
+                            eprintln!("> Failed to cache the result of '{}': {}", task.name, e);
+                        }
+                    }
+                    (attempt, Some(log_path), attempts)
+                }
+                Err(e) => {
+                    eprintln!(
+                        "> Failed to create the output file for '{}': {}",
+                        task.name, e
+                    );
+                    (Attempt::new(PassFail::Fail), None, 0)
+                }
+            }
+        }
+    };
 
-    TaskResult {
+    let task_result = TaskResult {
         name: task.name.to_string(),
-        command: command.to_string(),
+        command,
         stage: stage_number + 1,
-        result: get_result_passfail(command_results),
+        result: attempt.result,
         elapsed_time: start.elapsed().as_secs() as i64,
-        file_path: task.file_path.to_owned().unwrap(),
+        file_path: task.file_path.to_owned().unwrap_or_default(),
+        log_path,
+        attempts,
+        allow_failure: task.allow_failure.unwrap_or_default(),
+        exit_code: attempt.exit_code,
+        failed_line: attempt.failed_line,
+    };
+    reporter.task_finished(&task_result);
+    task_result
+}
+
+/// The outcome of a single attempt at running a Task
+struct Attempt {
+    result: PassFail,
+    /// The exit code, if the command exited normally
+    exit_code: Option<i32>,
+    /// The line of the script that failed, if the shell reported it
+    failed_line: Option<u32>,
+}
+
+impl Attempt {
+    fn new(result: PassFail) -> Self {
+        Attempt {
+            result,
+            exit_code: None,
+            failed_line: None,
+        }
+    }
+}
+
+/// Run a Task's command until it passes or runs out of attempts.
+///
+/// Returns the final attempt along with the number of attempts.
+fn run_attempts(
+    task: &Task,
+    command: &str,
+    log_file: &File,
+    stage_number: i8,
+    reporter: &dyn Reporter,
+) -> (Attempt, u32) {
+    let max_attempts = task.retries.unwrap_or(0) + 1;
+    let mut retry_delay = Duration::from_secs(task.retry_delay.unwrap_or(0));
+    let mut attempts = 0;
+    let mut attempt = Attempt::new(PassFail::Cancelled);
+
+    while attempts < max_attempts && !processes::is_cancelled() {
+        if attempts > 0 {
+            reporter.task_retrying(
+                &task.name,
+                stage_number + 1,
+                attempts + 1,
+                max_attempts,
+                retry_delay,
+            );
+            if !processes::sleep_unless_cancelled(retry_delay) {
+                attempt = Attempt::new(PassFail::Cancelled);
+                break;
+            }
+            retry_delay *= 2;
+        }
+        attempts += 1;
+        attempt = match log_file.try_clone() {
+            Ok(log_file) => run_attempt(task, command, log_file, stage_number + 1, reporter),
+            Err(e) => {
+                eprintln!(
+                    "> Failed to open the output file for '{}': {}",
+                    task.name, e
+                );
+                Attempt::new(PassFail::Fail)
+            }
+        };
+        if attempt.result == PassFail::Pass {
+            break;
+        }
+    }
+    (attempt, attempts)
+}
+
+/// Run a single attempt of a Task's command, or of its script.
+///
+/// The Task's process group is killed if it runs for longer than its `timeout`.
+fn run_attempt(
+    task: &Task,
+    command: &str,
+    log_file: File,
+    stage: i8,
+    reporter: &dyn Reporter,
+) -> Attempt {
+    let workdir = task.workdir.clone().unwrap_or(".".to_string());
+    let script = match task.script.as_ref() {
+        Some(script) => match ScriptFile::write(task, script) {
+            Ok(script) => Some(script),
+            Err(e) => {
+                eprintln!("> Failed to write the script of '{}': {}", task.name, e);
+                return Attempt::new(PassFail::Fail);
+            }
+        },
+        None => None,
+    };
+    let argv = match &script {
+        Some(script) => script.argv(task),
+        None => build_argv(task, command),
+    };
+    let Some((program, args)) = argv.and_then(|argv| {
+        let (program, args) = argv.split_first()?;
+        Some((program.to_owned(), args.to_vec()))
+    }) else {
+        eprintln!(
+            "> Failed to split the command of '{}' into args!",
+            task.name
+        );
+        return Attempt::new(PassFail::Fail);
+    };
+    // A Task can only read from the terminal while it's lent the terminal,
+    // so it reads nothing if another Task or the dashboard is using it
+    let terminal = processes::claim_terminal();
+    let stdin = match terminal.is_some() || !processes::stdin_is_terminal() {
+        true => Stdio::inherit(),
+        false => Stdio::null(),
+    };
+    let mut child = Command::new(program);
+    child
+        .current_dir(workdir)
+        .envs(task.env.iter().flatten())
+        .args(args)
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0);
+    if terminal.is_some() {
+        unsafe {
+            child.pre_exec(|| {
+                processes::enter_foreground();
+                Ok(())
+            });
+        }
+    }
+    let child = match child.spawn() {
+        Ok(child) => child,
+        // If the command can't be spawned, we get an error here
+        Err(error) => return Attempt::new(get_result_passfail(Err(error))),
+    };
+
+    let pid = child.id();
+    if let Some(terminal) = &terminal {
+        terminal.lend_to(pid);
+    }
+    processes::register(pid);
+    let timed_out = AtomicBool::new(false);
+    let (finished, finished_receiver) = mpsc::channel::<()>();
+    let status = std::thread::scope(|scope| {
+        if let Some(timeout) = task.timeout {
+            let timed_out = &timed_out;
+            scope.spawn(move || {
+                let waited = finished_receiver.recv_timeout(Duration::from_secs(timeout));
+                if waited == Err(RecvTimeoutError::Timeout) {
+                    timed_out.store(true, Ordering::SeqCst);
+                    processes::kill(pid);
+                }
+            });
+        }
+        let scan = |line: &str| {
+            if let Some(script) = &script {
+                script.scan(line)
+            }
+        };
+        let status = capture_output(child, log_file, &task.name, stage, reporter, scan);
+        let _ = finished.send(());
+        status
+    });
+    processes::unregister(pid);
+
+    // Ctrl-C is sent to the Task rather than to rox while the Task has the terminal
+    let interrupted = terminal.is_some()
+        && status.as_ref().ok().and_then(ExitStatus::signal) == Some(libc::SIGINT);
+    drop(terminal);
+    if interrupted {
+        unsafe {
+            libc::kill(libc::getpid(), libc::SIGINT);
+        }
+    }
+
+    let exit_code = status.as_ref().ok().and_then(ExitStatus::code);
+    let mut result = get_result_passfail(status);
+    if interrupted || (processes::is_cancelled() && result != PassFail::Pass) {
+        result = PassFail::Cancelled;
+    } else if timed_out.load(Ordering::SeqCst) {
+        reporter.task_timed_out(&task.name, stage, task.timeout.unwrap_or_default());
+        result = PassFail::TimedOut;
+    }
+    let failed_line = match result {
+        PassFail::Fail => script.as_ref().and_then(ScriptFile::failed_line),
+        _ => None,
+    };
+    Attempt {
+        result,
+        exit_code,
+        failed_line,
+    }
+}
+
+/// Stream a child's stdout & stderr to the terminal while
+/// also writing both to the log file, then wait for it to exit.
+///
+/// Each line of stderr is also passed to `scan_stderr`.
+fn capture_output(
+    mut child: Child,
+    log_file: File,
+    name: &str,
+    stage: i8,
+    reporter: &dyn Reporter,
+    scan_stderr: impl Fn(&str) + Sync,
+) -> Result<ExitStatus, std::io::Error> {
+    let log_file = Mutex::new(log_file);
+    let stdout = child.stdout.take().unwrap();
+    let stderr = child.stderr.take().unwrap();
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| {
+            tee_lines(stdout, &log_file, |line| {
+                reporter.output_line(name, stage, Stream::Stdout, line)
+            })
+        });
+        scope.spawn(|| {
+            tee_lines(stderr, &log_file, |line| {
+                scan_stderr(line);
+                reporter.output_line(name, stage, Stream::Stderr, line)
+            })
+        });
+    });
+
+    child.wait()
+}
+
+/// Read a stream line-by-line, passing each line to `report` and the log file
+fn tee_lines(stream: impl Read, log_file: &Mutex<File>, report: impl Fn(&str)) {
+    let mut reader = BufReader::new(stream);
+    let mut buffer = Vec::new();
+
+    while let Ok(bytes) = reader.read_until(b'\n', &mut buffer) {
+        if bytes == 0 {
+            break;
+        }
+        let line = String::from_utf8_lossy(&buffer);
+        let line = line.trim_end_matches(['\n', '\r']);
+        report(line);
+        let _ = writeln!(log_file.lock().unwrap(), "{}", line);
+        buffer.clear();
     }
 }
 
@@ -48,80 +396,277 @@ pub fn execute_tasks(
     stage_number: i8,
     task_map: &HashMap<String, Task>,
     parallel: bool,
+    reporter: &dyn Reporter,
 ) -> Vec<TaskResult> {
-    let task_stack: Vec<Task> = tasks
+    // Matrix Tasks are run as a group of their sub-Tasks
+    let task_stack: Vec<Task> = matrix::expand_task_names(&tasks, task_map)
         .iter()
-        .map(|task| {
-            task_map
-                .get(task)
-                .expect("Error! Task does not exist!")
-                .to_owned()
-        })
+        .filter_map(|task| task_map.get(task).cloned())
         .collect();
-    println!(
-        "> Running task(s): {:#?}",
-        &task_stack
-            .iter()
-            .map(|task| &task.name)
-            .collect::<Vec<&String>>()
-    );
+    let names: Vec<String> = task_stack.iter().map(|task| task.name.to_owned()).collect();
+    reporter.stage_started(stage_number + 1, &names);
 
-    // TODO: Add progress bars?
+    // Tasks that haven't started yet are skipped if execution is cancelled
+    let run_or_skip = |task: &Task| match processes::is_cancelled() {
+        true => skip_task(task, stage_number, reporter),
+        false => run_task(task, stage_number, reporter),
+    };
     if parallel {
-        return task_stack
-            .par_iter()
-            .map(|task| run_task(task, stage_number))
-            .collect();
+        task_stack.par_iter().map(run_or_skip).collect()
     } else {
-        return task_stack
-            .iter()
-            .map(|task| run_task(task, stage_number))
-            .collect();
+        task_stack.iter().map(run_or_skip).collect()
+    }
+}
+
+/// Report a Task as not having been run, due to an earlier failure or cancellation
+fn skip_task(task: &Task, stage_number: i8, reporter: &dyn Reporter) -> TaskResult {
+    let task_result = TaskResult {
+        name: task.name.to_owned(),
+        command: build_command(task),
+        stage: stage_number + 1,
+        result: PassFail::NotRun,
+        elapsed_time: 0,
+        file_path: task.file_path.to_owned().unwrap_or_default(),
+        log_path: None,
+        attempts: 0,
+        allow_failure: task.allow_failure.unwrap_or_default(),
+        exit_code: None,
+        failed_line: None,
+    };
+    reporter.task_finished(&task_result);
+    task_result
+}
+
+/// Report multiple Tasks as not having been run
+fn skip_tasks(
+    tasks: Vec<String>,
+    stage_number: i8,
+    task_map: &HashMap<String, Task>,
+    reporter: &dyn Reporter,
+) -> Vec<TaskResult> {
+    matrix::expand_task_names(&tasks, task_map)
+        .iter()
+        .filter_map(|name| task_map.get(name))
+        .map(|task| skip_task(task, stage_number, reporter))
+        .collect()
+}
+
+/// Execute Tasks along with their prerequisites, one layer at a time.
+///
+/// If any Task within a layer fails, the remaining layers depend on it
+/// and so are reported as not having been run, as they are if execution
+/// is cancelled.
+pub fn execute_layers(
+    layers: Vec<Vec<String>>,
+    stage_number: Option<i8>,
+    task_map: &HashMap<String, Task>,
+    parallel: bool,
+    reporter: &dyn Reporter,
+) -> Vec<TaskResult> {
+    let mut results: Vec<TaskResult> = Vec::new();
+    let mut failed = false;
+    for (layer_number, layer) in layers.into_iter().enumerate() {
+        let layer_stage = stage_number.unwrap_or(layer_number as i8);
+        if failed || processes::is_cancelled() {
+            results.extend(skip_tasks(layer, layer_stage, task_map, reporter));
+            continue;
+        }
+        let layer_results = execute_tasks(layer, layer_stage, task_map, parallel, reporter);
+        failed = layer_results.iter().any(TaskResult::is_failure);
+        results.extend(layer_results);
     }
+    results
+}
+
+/// Split off the Tasks that depend on one that failed or wasn't run,
+/// either directly or through another prerequisite within the layers.
+///
+/// Returns the layers of Tasks that can still run, along with the rest.
+fn split_blocked(
+    layers: Vec<Vec<String>>,
+    task_map: &HashMap<String, Task>,
+    blocked: &HashSet<String>,
+) -> (Vec<Vec<String>>, Vec<String>) {
+    let mut blocked = blocked.clone();
+    let mut skipped: Vec<String> = Vec::new();
+    let layers = layers
+        .into_iter()
+        .map(|layer| {
+            let (layer, layer_skipped): (Vec<String>, Vec<String>) =
+                layer.into_iter().partition(|name| {
+                    let depends_on = task_map
+                        .get(name)
+                        .and_then(|task| task.depends_on.clone())
+                        .unwrap_or_default();
+                    !depends_on
+                        .iter()
+                        .chain(matrix::expand_task_names(&depends_on, task_map).iter())
+                        .any(|dependency| blocked.contains(dependency))
+                });
+            blocked.extend(matrix::expand_task_names(&layer_skipped, task_map));
+            blocked.extend(layer_skipped.iter().cloned());
+            skipped.extend(layer_skipped);
+            layer
+        })
+        .filter(|layer| !layer.is_empty())
+        .collect();
+    (layers, skipped)
 }
 
 /// Execute a vector of Stages
+///
+/// Prerequisites of each stage's Tasks are run before the stage itself,
+/// but only once for the entire pipeline. Once a stage fails, the
+/// remaining stages are only run if the failure policy is to continue,
+/// and even then Tasks whose prerequisites failed are reported as not
+/// having been run.
 pub fn execute_stages(
     stages: &[Vec<String>],
     task_map: &HashMap<String, Task>,
     parallel: bool,
+    on_failure: &FailurePolicy,
+    reporter: &dyn Reporter,
 ) -> Vec<Vec<TaskResult>> {
-    let stage_results: Vec<Vec<TaskResult>> = stages
-        .iter()
-        .enumerate()
-        .map(|(stage_number, stage)| {
-            execute_tasks(stage.clone(), stage_number as i8, task_map, parallel)
-        })
-        .collect();
+    let mut completed: HashSet<String> = HashSet::new();
+    let mut blocked: HashSet<String> = HashSet::new();
+    let mut failed = false;
+    let mut stage_results: Vec<Vec<TaskResult>> = Vec::new();
+    for (stage_number, stage) in stages.iter().enumerate() {
+        let layers = resolve_execution_layers(stage, task_map, &completed);
+        completed.extend(layers.iter().flatten().cloned());
+
+        let stopped = failed && *on_failure == FailurePolicy::Stop;
+        let results = if stopped || processes::is_cancelled() {
+            skip_tasks(
+                layers.into_iter().flatten().collect(),
+                stage_number as i8,
+                task_map,
+                reporter,
+            )
+        } else {
+            let (layers, skipped) = split_blocked(layers, task_map, &blocked);
+            let mut results = execute_layers(
+                layers,
+                Some(stage_number as i8),
+                task_map,
+                parallel,
+                reporter,
+            );
+            results.extend(skip_tasks(skipped, stage_number as i8, task_map, reporter));
+            results
+        };
+        failed |= results.iter().any(TaskResult::is_failure);
+        blocked.extend(
+            results
+                .iter()
+                .filter(|result| result.is_failure() || result.result == PassFail::NotRun)
+                .map(|result| result.name.to_owned()),
+        );
+        stage_results.push(results);
+    }
     stage_results
     // TODO: Return a JobResults here
 }
 
+/// Log the results of a finished job, then report them
+fn finish_job(results: JobResults, reporter: &dyn Reporter) -> Result<JobResults, RoxError> {
+    logs::write_logs(&results)?;
+    reporter.job_finished(&results);
+    Ok(results)
+}
+
 /// Execute Pipeline
-pub fn execute_pipeline(pipeline: Pipeline, task_map: &HashMap<String, Task>, parallel: bool) {
+///
+/// Optionally shows a live dashboard of the execution.
+pub fn execute_pipeline(
+    pipeline: Pipeline,
+    task_map: &HashMap<String, Task>,
+    parallel: bool,
+    tui: bool,
+    reporter: &dyn Reporter,
+) -> Result<JobResults, RoxError> {
+    let names: Vec<String> = pipeline.stages.iter().flatten().cloned().collect();
+    let file_path = pipeline.file_path.clone().unwrap_or_default();
+    check_tasks_exist(&names, &file_path, task_map)?;
+    let _execution = processes::start_execution();
     let execution_start = chrono::Utc::now().to_rfc3339();
-    let execution_results = execute_stages(&pipeline.stages, task_map, parallel);
+    reporter.job_started(&pipeline.name);
+    let execution_results = match tui {
+        true => dashboard::run_dashboard(&pipeline, task_map, parallel, reporter),
+        false => execute_stages(
+            &pipeline.stages,
+            task_map,
+            parallel,
+            &pipeline.on_failure,
+            reporter,
+        ),
+    };
     let results = JobResults {
         job_name: pipeline.name.to_string(),
         execution_time: execution_start,
         results: execution_results.into_iter().flatten().collect(),
     };
-    results.log_results();
-    results.display_results();
-    results.check_results();
+    finish_job(results, reporter)
 }
 
 /// Execute a single user-defined Task
-pub fn execute_task(task: Task) {
+///
+/// Any prerequisites are run first, in parallel where possible.
+pub fn execute_task(
+    task: Task,
+    task_map: &HashMap<String, Task>,
+    reporter: &dyn Reporter,
+) -> Result<JobResults, RoxError> {
+    let file_path = task.file_path.clone().unwrap_or_default();
+    check_tasks_exist(&[task.name.to_owned()], &file_path, task_map)?;
+    let _execution = processes::start_execution();
     let execution_start = chrono::Utc::now().to_rfc3339();
-    let execution_results: TaskResult = run_task(&task, 0);
+    reporter.job_started(&task.name);
+    let layers = resolve_execution_layers(&[task.name.to_owned()], task_map, &HashSet::new());
+    let execution_results = execute_layers(layers, None, task_map, true, reporter);
     let results = JobResults {
         job_name: task.name.to_string(),
         execution_time: execution_start,
-        results: vec![execution_results],
+        results: execution_results,
+    };
+    finish_job(results, reporter)
+}
+
+#[test]
+fn build_command_with_args() {
+    let mut task = Task {
+        name: "test".to_owned(),
+        command: Some("cargo test".to_owned()),
+        ..Default::default()
+    };
+    assert_eq!(build_command(&task), "cargo test");
+
+    task.args = Some(vec!["--".to_owned(), "--nocapture".to_owned()]);
+    assert_eq!(build_command(&task), "cargo test -- --nocapture");
+
+    task.command = Some("cargo test {args} --quiet".to_owned());
+    task.args = Some(vec!["it's".to_owned()]);
+    assert_eq!(build_command(&task), "cargo test 'it'\\''s' --quiet");
+}
+
+#[test]
+fn build_argv_uses_shell() {
+    use crate::models::Shell;
+
+    let mut task = Task {
+        name: "test".to_owned(),
+        ..Default::default()
     };
+    let argv = |task: &Task| build_argv(task, "echo 'a b'");
+    assert_eq!(argv(&task).unwrap(), vec!["sh", "-c", "echo 'a b'"]);
+
+    task.shell = Some(Shell::Line("bash -euo pipefail -c".to_owned()));
+    assert_eq!(
+        argv(&task).unwrap(),
+        vec!["bash", "-euo", "pipefail", "-c", "echo 'a b'"]
+    );
 
-    results.log_results();
-    results.display_results();
-    results.check_results();
+    task.shell = Some(Shell::Argv(Vec::new()));
+    assert_eq!(argv(&task).unwrap(), vec!["echo", "a b"]);
+    assert!(build_argv(&task, "echo 'a b").is_none());
 }
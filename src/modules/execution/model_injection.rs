@@ -1,11 +1,15 @@
 use crate::models;
+use std::collections::HashMap;
 
 /// Get used Template's information and inject set values
+///
+/// Positional `values` replace the Template's `symbols`, while named
+/// params are replaced by the Task's `with` bindings or their defaults.
 pub fn inject_template_values(mut task: models::Task, template: &models::Template) -> models::Task {
     task.command = {
         let mut template_command = template.command.to_owned();
         let template_symbols = template.symbols.to_owned();
-        let task_values = task.values.as_ref().unwrap();
+        let task_values = task.values.clone().unwrap_or_default();
 
         for i in 0..task_values.len() {
             template_command = template_command.replace(
@@ -13,38 +17,113 @@ pub fn inject_template_values(mut task: models::Task, template: &models::Templat
                 task_values.get(i).unwrap(),
             );
         }
+
+        for param in template.params.iter().flatten() {
+            let value = task
+                .with
+                .as_ref()
+                .and_then(|with| with.get(&param.name))
+                .or(param.default.as_ref());
+            if let Some(value) = value {
+                template_command = template_command.replace(&param.placeholder(), value);
+            }
+        }
         Some(template_command)
     };
     task
 }
 
+/// Override a Task's named Template bindings with values
+/// supplied at invocation and re-inject the Template.
+pub fn override_template_values(
+    mut task: models::Task,
+    template: &models::Template,
+    overrides: HashMap<String, String>,
+) -> Result<models::Task, models::ValidationError> {
+    task.with.get_or_insert_with(HashMap::new).extend(overrides);
+    template.validate_bindings(&task)?;
+    Ok(inject_template_values(task, template))
+}
+
 #[test]
 fn inject_template_values_valid() {
     let test_task = models::Task {
         name: "Test".to_string(),
         command: None,
+        script: None,
         file_path: None,
         uses: None,
         values: Some(vec!["1".to_owned(), "2".to_owned()]),
+        with: None,
         description: None,
         hide: None,
         workdir: None,
+        shell: None,
+        depends_on: None,
+        env: None,
+        env_file: None,
+        inputs: None,
+        outputs: None,
+        timeout: None,
+        retries: None,
+        retry_delay: None,
+        allow_failure: None,
+        matrix: None,
+        args: None,
     };
     let test_template = models::Template {
         name: "Test".to_string(),
         command: "This is {one} and {two}".to_owned(),
         symbols: vec!["{one}".to_owned(), "{two}".to_owned()],
+        params: None,
+        file_path: None,
     };
     let output_task = inject_template_values(test_task, &test_template);
     assert_eq!(output_task.command.unwrap(), "This is 1 and 2".to_owned())
 }
 
+#[test]
+fn inject_named_template_values() {
+    let test_task = models::Task {
+        name: "Test".to_string(),
+        uses: Some("Test".to_owned()),
+        with: Some(HashMap::from([("two".to_owned(), "2".to_owned())])),
+        ..Default::default()
+    };
+    let test_template = models::Template {
+        name: "Test".to_string(),
+        command: "This is {one} and {two}".to_owned(),
+        symbols: Vec::new(),
+        params: Some(vec![
+            models::TemplateParam {
+                name: "one".to_owned(),
+                default: Some("1".to_owned()),
+                description: None,
+            },
+            models::TemplateParam {
+                name: "two".to_owned(),
+                default: None,
+                description: None,
+            },
+        ]),
+        file_path: None,
+    };
+    let output_task = inject_template_values(test_task.clone(), &test_template);
+    assert_eq!(output_task.command.unwrap(), "This is 1 and 2".to_owned());
+
+    let overrides = HashMap::from([("one".to_owned(), "one".to_owned())]);
+    let output_task = override_template_values(test_task, &test_template, overrides).unwrap();
+    assert_eq!(output_task.command.unwrap(), "This is one and 2".to_owned());
+}
+
 /// Inject additional metadata into each Task and sort based on name.
+///
+/// Tasks that were already loaded from another file keep their file path.
 pub fn inject_task_metadata(tasks: Vec<models::Task>, file_path: &str) -> Vec<models::Task> {
     let mut sorted_tasks: Vec<models::Task> = tasks
         .into_iter()
         .map(|mut task| {
-            task.file_path = Some(file_path.to_owned());
+            task.file_path.get_or_insert_with(|| file_path.to_owned());
 
             if task.description.is_none() {
                 task.command.clone_into(&mut task.description)
@@ -52,6 +131,6 @@ pub fn inject_task_metadata(tasks: Vec<models::Task>, file_path: &str) -> Vec<mo
             task
         })
         .collect();
-    sorted_tasks.sort_by(|x, y| x.name.to_lowercase().cmp(&y.name.to_lowercase()));
+    sorted_tasks.sort_by_key(|task| task.name.to_lowercase());
     sorted_tasks
 }
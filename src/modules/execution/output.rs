@@ -1,6 +1,44 @@
 use crate::models::{JobResults, PassFail};
+use clap::builder::PossibleValue;
 use cli_table::{format::Justify, print_stdout, Cell, Style, Table};
 use colored::Colorize;
+use serde::Serialize;
+
+/// The format used for Rox's output
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum OutputFormat {
+    /// Human-readable text and tables
+    #[default]
+    Text,
+    /// A single JSON document once execution has finished
+    Json,
+    /// A stream of newline-delimited JSON events
+    Ndjson,
+}
+
+impl clap::ValueEnum for OutputFormat {
+    fn value_variants<'a>() -> &'a [Self] {
+        &[OutputFormat::Text, OutputFormat::Json, OutputFormat::Ndjson]
+    }
+
+    fn to_possible_value(&self) -> Option<PossibleValue> {
+        Some(match self {
+            OutputFormat::Text => PossibleValue::new("text"),
+            OutputFormat::Json => PossibleValue::new("json"),
+            OutputFormat::Ndjson => PossibleValue::new("ndjson"),
+        })
+    }
+}
+
+/// Print a value as JSON on a single line
+pub fn print_json_line<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string(value).unwrap());
+}
+
+/// Print a value as a pretty JSON document
+pub fn print_json<T: Serialize>(value: &T) {
+    println!("{}", serde_json::to_string_pretty(value).unwrap());
+}
 
 /// Print the execution results in a pretty table format
 pub fn display_execution_results(results: &JobResults) {
@@ -17,14 +55,39 @@ pub fn display_execution_results(results: &JobResults) {
                     .green()
                     .cell()
                     .justify(Justify::Center),
-                PassFail::Fail => result
+                PassFail::Fail | PassFail::TimedOut if result.allow_failure => {
+                    format!("{} (allowed)", result.result)
+                        .yellow()
+                        .cell()
+                        .justify(Justify::Center)
+                }
+                PassFail::Fail | PassFail::TimedOut => result
                     .result
                     .to_string()
                     .red()
                     .cell()
                     .justify(Justify::Center),
+                PassFail::Cancelled => result
+                    .result
+                    .to_string()
+                    .magenta()
+                    .cell()
+                    .justify(Justify::Center),
+                PassFail::NotRun => result
+                    .result
+                    .to_string()
+                    .dimmed()
+                    .cell()
+                    .justify(Justify::Center),
+                PassFail::Cached => result
+                    .result
+                    .to_string()
+                    .cyan()
+                    .cell()
+                    .justify(Justify::Center),
             },
             result.elapsed_time.cell().justify(Justify::Center),
+            result.attempts.cell().justify(Justify::Center),
         ])
     }
 
@@ -36,6 +99,7 @@ pub fn display_execution_results(results: &JobResults) {
                 "Stage".yellow().cell().bold(true),
                 "Result".yellow().cell().bold(true),
                 "Run Time (sec)".yellow().cell().bold(true),
+                "Attempts".yellow().cell().bold(true),
             ])
             .bold(true),
     )
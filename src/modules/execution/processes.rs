@@ -0,0 +1,256 @@
+//! Tracking of running Task processes so that they can be cancelled.
+//!
+//! Each Task is spawned in its own process group, which allows
+//! signals to reach every process started by the Task's command.
+//! As only the terminal's foreground process group can read from it,
+//! the terminal is lent to a Task's process group while it runs.
+use std::fs::File;
+use std::io::Read;
+use std::os::fd::FromRawFd;
+use std::sync::atomic::{AtomicBool, AtomicI32, AtomicUsize, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+static RUNNING: Mutex<Vec<u32>> = Mutex::new(Vec::new());
+static TERMINAL: Mutex<()> = Mutex::new(());
+static CANCELLED: AtomicBool = AtomicBool::new(false);
+static WATCHING: AtomicBool = AtomicBool::new(false);
+static EXECUTING: AtomicUsize = AtomicUsize::new(0);
+/// The write end of the pipe that received signals are passed through
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+/// How long processes are given to exit before being killed
+pub const GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Register a newly spawned process group.
+///
+/// If execution was cancelled while the process was being
+/// spawned, the process group is killed immediately.
+pub fn register(pid: u32) {
+    let mut running = RUNNING.lock().unwrap();
+    if is_cancelled() {
+        signal_group(pid, libc::SIGKILL);
+    }
+    running.push(pid);
+}
+
+/// Stop tracking a process group after it has exited
+pub fn unregister(pid: u32) {
+    RUNNING
+        .lock()
+        .unwrap()
+        .retain(|running_pid| *running_pid != pid);
+}
+
+/// Whether the current execution has been cancelled
+pub fn is_cancelled() -> bool {
+    CANCELLED.load(Ordering::SeqCst)
+}
+
+/// Whether Tasks are being rerun on changes, in which case
+/// an interrupt should stop everything immediately
+pub fn is_watching() -> bool {
+    WATCHING.load(Ordering::SeqCst)
+}
+
+/// Mark that Tasks are being rerun on changes
+pub fn set_watching() {
+    WATCHING.store(true, Ordering::SeqCst);
+}
+
+/// Marks that Tasks are being executed until it's dropped
+pub struct ExecutionGuard;
+
+impl Drop for ExecutionGuard {
+    fn drop(&mut self) {
+        EXECUTING.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Mark that Tasks are being executed, so that an interrupt
+/// stops them gracefully rather than exiting immediately
+pub fn start_execution() -> ExecutionGuard {
+    EXECUTING.fetch_add(1, Ordering::SeqCst);
+    ExecutionGuard
+}
+
+/// Whether any Tasks are being executed
+pub fn is_executing() -> bool {
+    EXECUTING.load(Ordering::SeqCst) > 0
+}
+
+/// Sleep for a duration, waking early if execution is cancelled.
+///
+/// Returns whether the full duration passed without a cancellation.
+pub fn sleep_unless_cancelled(duration: Duration) -> bool {
+    let start = Instant::now();
+    while !is_cancelled() {
+        let remaining = duration.saturating_sub(start.elapsed());
+        if remaining.is_zero() {
+            return true;
+        }
+        std::thread::sleep(remaining.min(Duration::from_millis(50)));
+    }
+    false
+}
+
+/// Allow Tasks to run again after a cancellation
+pub fn reset() {
+    CANCELLED.store(false, Ordering::SeqCst);
+}
+
+/// Cancel the current execution by forwarding a signal to every running
+/// process group, followed by SIGKILL for any that remain after the
+/// grace period.
+pub fn cancel(signal: libc::c_int) {
+    let pids = {
+        let running = RUNNING.lock().unwrap();
+        CANCELLED.store(true, Ordering::SeqCst);
+        running.clone()
+    };
+    for pid in pids.iter() {
+        signal_group(*pid, signal);
+    }
+
+    std::thread::spawn(move || {
+        std::thread::sleep(GRACE_PERIOD);
+        kill_remaining(&pids);
+    });
+}
+
+/// Immediately kill every running process group
+pub fn kill_all() {
+    let running = RUNNING.lock().unwrap();
+    CANCELLED.store(true, Ordering::SeqCst);
+    for pid in running.iter() {
+        signal_group(*pid, libc::SIGKILL);
+    }
+}
+
+/// Immediately kill a single process group
+pub fn kill(pid: u32) {
+    signal_group(pid, libc::SIGKILL);
+}
+
+/// Kill any of the given process groups that are still running
+fn kill_remaining(pids: &[u32]) {
+    let running = RUNNING.lock().unwrap();
+    for pid in pids.iter().filter(|pid| running.contains(pid)) {
+        signal_group(*pid, libc::SIGKILL);
+    }
+}
+
+fn signal_group(pid: u32, signal: libc::c_int) {
+    // A negative pid targets the entire process group
+    unsafe {
+        libc::kill(-(pid as libc::pid_t), signal);
+    }
+}
+
+extern "C" fn pass_signal(signal: libc::c_int) {
+    // Writing to a pipe is one of the few things that's safe within a signal handler
+    let byte = signal as u8;
+    unsafe {
+        libc::write(
+            SIGNAL_PIPE.load(Ordering::SeqCst),
+            &byte as *const u8 as *const libc::c_void,
+            1,
+        );
+    }
+}
+
+/// Call a function with each SIGINT, SIGTERM or SIGHUP that's received.
+///
+/// The function is called on a thread of its own,
+/// so it isn't limited like a signal handler is.
+pub fn handle_signals(handler: impl Fn(libc::c_int) + Send + 'static) -> std::io::Result<()> {
+    let mut fds = [0; 2];
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    // Tasks shouldn't inherit either end of the pipe
+    for fd in fds {
+        unsafe {
+            libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC);
+        }
+    }
+    SIGNAL_PIPE.store(fds[1], Ordering::SeqCst);
+
+    let mut receiver = unsafe { File::from_raw_fd(fds[0]) };
+    std::thread::spawn(move || {
+        let mut signal = [0u8];
+        while receiver.read_exact(&mut signal).is_ok() {
+            handler(signal[0] as libc::c_int);
+        }
+    });
+
+    for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+        let handler = pass_signal as extern "C" fn(libc::c_int) as libc::sighandler_t;
+        if unsafe { libc::signal(signal, handler) } == libc::SIG_ERR {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+/// Exclusive use of the terminal, which is handed back to rox once dropped
+pub struct TerminalGuard {
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl TerminalGuard {
+    /// Make a Task's process group the foreground one, so that it can read from the terminal
+    pub fn lend_to(&self, pid: u32) {
+        set_foreground(pid as libc::pid_t);
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        set_foreground(unsafe { libc::getpgrp() });
+    }
+}
+
+/// Whether stdin is a terminal, in which case only its foreground process group can read it
+pub fn stdin_is_terminal() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
+
+/// Claim the terminal for a Task or the dashboard.
+///
+/// Returns `None` if rox isn't in the foreground of a terminal,
+/// or if the terminal is already in use.
+pub fn claim_terminal() -> Option<TerminalGuard> {
+    let in_foreground =
+        stdin_is_terminal() && unsafe { libc::tcgetpgrp(libc::STDIN_FILENO) == libc::getpgrp() };
+    if !in_foreground {
+        return None;
+    }
+    let guard = TERMINAL.try_lock().ok()?;
+    Some(TerminalGuard { _guard: guard })
+}
+
+/// Make the calling process's group the terminal's foreground one.
+///
+/// Called by a Task between fork & exec, as well as by rox after lending
+/// it the terminal, so that the Task can't read from the terminal before
+/// it's in the foreground. Only async-signal-safe functions are used.
+pub fn enter_foreground() {
+    set_foreground(unsafe { libc::getpgrp() });
+}
+
+/// Make a process group the terminal's foreground one.
+///
+/// SIGTTOU is blocked while doing so, as it would
+/// otherwise stop a background process group.
+fn set_foreground(pgid: libc::pid_t) {
+    unsafe {
+        let mut block: libc::sigset_t = std::mem::zeroed();
+        let mut previous: libc::sigset_t = std::mem::zeroed();
+        libc::sigemptyset(&mut block);
+        libc::sigaddset(&mut block, libc::SIGTTOU);
+        libc::pthread_sigmask(libc::SIG_BLOCK, &block, &mut previous);
+        libc::tcsetpgrp(libc::STDIN_FILENO, pgid);
+        libc::pthread_sigmask(libc::SIG_SETMASK, &previous, std::ptr::null_mut());
+    }
+}
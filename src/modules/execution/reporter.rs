@@ -0,0 +1,447 @@
+//! Reporting on executions as they happen.
+//!
+//! The executor emits an event at each step of a job to a [`Reporter`],
+//! and any number of reporters can be attached at once via [`Reporters`].
+use super::output::{display_execution_results, print_json, print_json_line};
+use crate::logs;
+use crate::models::{JobResults, PassFail, TaskResult};
+use crate::modules::junit;
+use serde::Serialize;
+use std::time::Duration;
+
+/// Which of a Task's output streams a line was written to
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+/// Receives events as a job executes.
+///
+/// Every method does nothing by default, so only the events of interest
+/// need to be handled. Tasks may run in parallel, so events can arrive
+/// from several threads at once.
+pub trait Reporter: Sync {
+    /// A Task or Pipeline has started
+    fn job_started(&self, _job_name: &str) {}
+
+    /// A group of Tasks has started, either a Pipeline stage
+    /// or a layer of prerequisites that have to run first
+    fn stage_started(&self, _stage: i8, _tasks: &[String]) {}
+
+    /// A Task's command is about to run
+    fn task_started(&self, _name: &str, _stage: i8, _command: &str) {}
+
+    /// A Task wrote a line to stdout or stderr
+    fn output_line(&self, _name: &str, _stage: i8, _stream: Stream, _line: &str) {}
+
+    /// A Task failed and is about to be retried after a delay
+    fn task_retrying(
+        &self,
+        _name: &str,
+        _stage: i8,
+        _attempt: u32,
+        _max_attempts: u32,
+        _delay: Duration,
+    ) {
+    }
+
+    /// A Task's command ran for longer than its timeout and was killed
+    fn task_timed_out(&self, _name: &str, _stage: i8, _timeout: u64) {}
+
+    /// A Task has finished, including when it was cached or not run
+    fn task_finished(&self, _result: &TaskResult) {}
+
+    /// The job has finished and its results have been logged
+    fn job_finished(&self, _results: &JobResults) {}
+}
+
+impl<R: Reporter + ?Sized> Reporter for &R {
+    fn job_started(&self, job_name: &str) {
+        (**self).job_started(job_name)
+    }
+
+    fn stage_started(&self, stage: i8, tasks: &[String]) {
+        (**self).stage_started(stage, tasks)
+    }
+
+    fn task_started(&self, name: &str, stage: i8, command: &str) {
+        (**self).task_started(name, stage, command)
+    }
+
+    fn output_line(&self, name: &str, stage: i8, stream: Stream, line: &str) {
+        (**self).output_line(name, stage, stream, line)
+    }
+
+    fn task_retrying(
+        &self,
+        name: &str,
+        stage: i8,
+        attempt: u32,
+        max_attempts: u32,
+        delay: Duration,
+    ) {
+        (**self).task_retrying(name, stage, attempt, max_attempts, delay)
+    }
+
+    fn task_timed_out(&self, name: &str, stage: i8, timeout: u64) {
+        (**self).task_timed_out(name, stage, timeout)
+    }
+
+    fn task_finished(&self, result: &TaskResult) {
+        (**self).task_finished(result)
+    }
+
+    fn job_finished(&self, results: &JobResults) {
+        (**self).job_finished(results)
+    }
+}
+
+/// Several reporters, each of which is sent every event in the order they were added
+#[derive(Default)]
+pub struct Reporters<'a> {
+    reporters: Vec<Box<dyn Reporter + 'a>>,
+}
+
+impl<'a> Reporters<'a> {
+    pub fn new() -> Self {
+        Reporters::default()
+    }
+
+    /// Attach another reporter
+    pub fn add(&mut self, reporter: impl Reporter + 'a) {
+        self.reporters.push(Box::new(reporter));
+    }
+
+    /// Attach another reporter, builder-style
+    pub fn with(mut self, reporter: impl Reporter + 'a) -> Self {
+        self.add(reporter);
+        self
+    }
+}
+
+impl Reporter for Reporters<'_> {
+    fn job_started(&self, job_name: &str) {
+        self.reporters.iter().for_each(|r| r.job_started(job_name))
+    }
+
+    fn stage_started(&self, stage: i8, tasks: &[String]) {
+        self.reporters
+            .iter()
+            .for_each(|r| r.stage_started(stage, tasks))
+    }
+
+    fn task_started(&self, name: &str, stage: i8, command: &str) {
+        self.reporters
+            .iter()
+            .for_each(|r| r.task_started(name, stage, command))
+    }
+
+    fn output_line(&self, name: &str, stage: i8, stream: Stream, line: &str) {
+        self.reporters
+            .iter()
+            .for_each(|r| r.output_line(name, stage, stream, line))
+    }
+
+    fn task_retrying(
+        &self,
+        name: &str,
+        stage: i8,
+        attempt: u32,
+        max_attempts: u32,
+        delay: Duration,
+    ) {
+        self.reporters
+            .iter()
+            .for_each(|r| r.task_retrying(name, stage, attempt, max_attempts, delay))
+    }
+
+    fn task_timed_out(&self, name: &str, stage: i8, timeout: u64) {
+        self.reporters
+            .iter()
+            .for_each(|r| r.task_timed_out(name, stage, timeout))
+    }
+
+    fn task_finished(&self, result: &TaskResult) {
+        self.reporters.iter().for_each(|r| r.task_finished(result))
+    }
+
+    fn job_finished(&self, results: &JobResults) {
+        self.reporters.iter().for_each(|r| r.job_finished(results))
+    }
+}
+
+/// Human-readable progress, followed by a table of the results
+pub struct ConsoleReporter {
+    live: bool,
+}
+
+impl ConsoleReporter {
+    pub fn new() -> Self {
+        ConsoleReporter { live: true }
+    }
+
+    /// Only print the results once the job has finished,
+    /// e.g. when the dashboard is showing the progress instead
+    pub fn summary() -> Self {
+        ConsoleReporter { live: false }
+    }
+}
+
+impl Default for ConsoleReporter {
+    fn default() -> Self {
+        ConsoleReporter::new()
+    }
+}
+
+impl Reporter for ConsoleReporter {
+    fn stage_started(&self, _stage: i8, tasks: &[String]) {
+        if self.live {
+            println!("> Running task(s): {:#?}", tasks);
+        }
+    }
+
+    fn task_started(&self, _name: &str, _stage: i8, command: &str) {
+        if self.live {
+            println!("> Running command: '{}'", command);
+        }
+    }
+
+    fn output_line(&self, _name: &str, _stage: i8, stream: Stream, line: &str) {
+        if !self.live {
+            return;
+        }
+        match stream {
+            Stream::Stdout => println!("{}", line),
+            Stream::Stderr => eprintln!("{}", line),
+        }
+    }
+
+    fn task_retrying(
+        &self,
+        name: &str,
+        _stage: i8,
+        attempt: u32,
+        max_attempts: u32,
+        delay: Duration,
+    ) {
+        if self.live {
+            println!(
+                "> Retrying '{}' in {}s (attempt {} of {})",
+                name,
+                delay.as_secs(),
+                attempt,
+                max_attempts
+            );
+        }
+    }
+
+    fn task_timed_out(&self, name: &str, _stage: i8, timeout: u64) {
+        if self.live {
+            println!("> Task '{}' timed out after {}s", name, timeout);
+        }
+    }
+
+    fn task_finished(&self, result: &TaskResult) {
+        if !self.live {
+            return;
+        }
+        if result.result == PassFail::Cached {
+            println!("> Skipping '{}', its inputs are unchanged", result.name);
+        }
+        if let Some(line) = result.failed_line {
+            println!(
+                "> Task '{}' failed at line {} of its script",
+                result.name, line
+            );
+        }
+    }
+
+    fn job_finished(&self, results: &JobResults) {
+        println!("> Log file written to: {}", logs::log_file_name(results));
+        display_execution_results(results);
+    }
+}
+
+/// A single JSON document of the results, once the job has finished
+pub struct JsonReporter;
+
+impl Reporter for JsonReporter {
+    fn job_finished(&self, results: &JobResults) {
+        print_json(results);
+    }
+}
+
+/// A JUnit XML report of the results, written once the job has finished
+pub struct JunitReporter {
+    path: String,
+}
+
+impl JunitReporter {
+    pub fn new(path: impl Into<String>) -> Self {
+        JunitReporter { path: path.into() }
+    }
+}
+
+impl Reporter for JunitReporter {
+    fn job_finished(&self, results: &JobResults) {
+        if let Err(e) = junit::write_junit_report(std::slice::from_ref(results), &self.path) {
+            eprintln!(
+                "> Failed to write the JUnit report to '{}': {}",
+                self.path, e
+            );
+        }
+    }
+}
+
+/// An event emitted during execution, as streamed by the `ndjson` output format
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum ExecutionEvent {
+    JobStarted {
+        job_name: String,
+    },
+    StageStarted {
+        stage: i8,
+        tasks: Vec<String>,
+    },
+    Started {
+        name: String,
+        stage: i8,
+        command: String,
+    },
+    Output {
+        name: String,
+        stage: i8,
+        stream: Stream,
+        line: String,
+    },
+    Retrying {
+        name: String,
+        stage: i8,
+        attempt: u32,
+        max_attempts: u32,
+        delay_secs: u64,
+    },
+    TimedOut {
+        name: String,
+        stage: i8,
+        timeout: u64,
+    },
+    Finished(TaskResult),
+    JobFinished {
+        job: JobResults,
+    },
+}
+
+/// Passes every event, as an [`ExecutionEvent`], to a function
+pub struct EventReporter<F: Fn(ExecutionEvent) + Sync> {
+    handle: F,
+}
+
+impl<F: Fn(ExecutionEvent) + Sync> EventReporter<F> {
+    pub fn new(handle: F) -> Self {
+        EventReporter { handle }
+    }
+}
+
+/// Stream every event as a line of JSON
+pub fn ndjson_reporter() -> EventReporter<impl Fn(ExecutionEvent) + Sync> {
+    EventReporter::new(|event| print_json_line(&event))
+}
+
+impl<F: Fn(ExecutionEvent) + Sync> Reporter for EventReporter<F> {
+    fn job_started(&self, job_name: &str) {
+        (self.handle)(ExecutionEvent::JobStarted {
+            job_name: job_name.to_owned(),
+        })
+    }
+
+    fn stage_started(&self, stage: i8, tasks: &[String]) {
+        (self.handle)(ExecutionEvent::StageStarted {
+            stage,
+            tasks: tasks.to_vec(),
+        })
+    }
+
+    fn task_started(&self, name: &str, stage: i8, command: &str) {
+        (self.handle)(ExecutionEvent::Started {
+            name: name.to_owned(),
+            stage,
+            command: command.to_owned(),
+        })
+    }
+
+    fn output_line(&self, name: &str, stage: i8, stream: Stream, line: &str) {
+        (self.handle)(ExecutionEvent::Output {
+            name: name.to_owned(),
+            stage,
+            stream,
+            line: line.to_owned(),
+        })
+    }
+
+    fn task_retrying(
+        &self,
+        name: &str,
+        stage: i8,
+        attempt: u32,
+        max_attempts: u32,
+        delay: Duration,
+    ) {
+        (self.handle)(ExecutionEvent::Retrying {
+            name: name.to_owned(),
+            stage,
+            attempt,
+            max_attempts,
+            delay_secs: delay.as_secs(),
+        })
+    }
+
+    fn task_timed_out(&self, name: &str, stage: i8, timeout: u64) {
+        (self.handle)(ExecutionEvent::TimedOut {
+            name: name.to_owned(),
+            stage,
+            timeout,
+        })
+    }
+
+    fn task_finished(&self, result: &TaskResult) {
+        (self.handle)(ExecutionEvent::Finished(result.clone()))
+    }
+
+    fn job_finished(&self, results: &JobResults) {
+        (self.handle)(ExecutionEvent::JobFinished {
+            job: results.clone(),
+        })
+    }
+}
+
+#[test]
+fn reporters_fan_out_events() {
+    use std::sync::Mutex;
+
+    let first = Mutex::new(Vec::new());
+    let second = Mutex::new(Vec::new());
+    let reporters = Reporters::new()
+        .with(EventReporter::new(|event| {
+            first.lock().unwrap().push(event)
+        }))
+        .with(EventReporter::new(|event| {
+            second.lock().unwrap().push(event)
+        }));
+
+    reporters.job_started("build");
+    reporters.output_line("build", 1, Stream::Stderr, "warning");
+    drop(reporters);
+
+    for events in [first, second] {
+        let events = events.into_inner().unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(matches!(
+            &events[1],
+            ExecutionEvent::Output { stream: Stream::Stderr, line, .. } if line == "warning"
+        ));
+    }
+}
@@ -0,0 +1,234 @@
+//! Running a Task's multi-line `script` from a temporary file
+//!
+//! The script is passed to the Task's shell as a file rather than with `-c`,
+//! which lets the shell report the line that failed. Bash reports it through
+//! an `EXIT` trap, while other shells and interpreters are relied upon to
+//! mention the script's path and line in their error messages.
+//!
+//! Both files live in a private directory of their own, so that other users
+//! can neither read the script nor swap in files of their own.
+use crate::models::Task;
+use crate::utils::{sanitize_filename, shell_quote};
+use std::ffi::{CString, OsString};
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::ffi::{OsStrExt, OsStringExt};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Create a uniquely named directory that only the current user can access
+fn create_private_dir() -> io::Result<PathBuf> {
+    let template = std::env::temp_dir().join("rox-XXXXXX");
+    let mut template = CString::new(template.as_os_str().as_bytes())?.into_bytes_with_nul();
+    // mkdtemp replaces the X's in place and creates the directory with mode 0700
+    if unsafe { libc::mkdtemp(template.as_mut_ptr().cast()) }.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    template.pop();
+    Ok(PathBuf::from(OsString::from_vec(template)))
+}
+
+/// A script written to a temporary file, which is removed once dropped
+pub struct ScriptFile {
+    dir: PathBuf,
+    path: PathBuf,
+    trap_path: Option<PathBuf>,
+    reported_line: Mutex<Option<u32>>,
+}
+
+impl ScriptFile {
+    /// Write a Task's script to a temporary file.
+    ///
+    /// Without a shell the file is made executable, so the script needs a shebang.
+    pub fn write(task: &Task, script: &str) -> io::Result<Self> {
+        let shell = super::shell_argv(task).unwrap_or_default();
+        let dir = create_private_dir()?;
+        let path = dir.join(sanitize_filename(&task.name));
+
+        // The traps share the script's first line so that line numbers are unchanged
+        let is_bash = shell
+            .first()
+            .is_some_and(|program| Path::new(program).ends_with("bash"));
+        let trap_path = is_bash.then(|| path.with_extension("line"));
+        let contents = match &trap_path {
+            Some(trap_path) => format!(
+                "{} {}",
+                bash_line_trap(&shell_quote(&trap_path.to_string_lossy())),
+                script
+            ),
+            None => script.to_owned(),
+        };
+        let written = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(if shell.is_empty() { 0o700 } else { 0o600 })
+            .open(&path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()));
+        if let Err(e) = written {
+            let _ = fs::remove_dir_all(&dir);
+            return Err(e);
+        }
+
+        Ok(ScriptFile {
+            dir,
+            path,
+            trap_path,
+            reported_line: Mutex::new(None),
+        })
+    }
+
+    /// Build the program & args that run the script, passing the Task's
+    /// pass-through arguments on to it. The flag that makes the shell run
+    /// its next arg as code is dropped, see [`drop_code_flag`].
+    ///
+    /// Returns `None` if the shell has an unclosed quote.
+    pub fn argv(&self, task: &Task) -> Option<Vec<String>> {
+        let mut argv = super::shell_argv(task)?;
+        drop_code_flag(&mut argv);
+        argv.push(self.path.to_string_lossy().into_owned());
+        argv.extend(task.args.iter().flatten().cloned());
+        Some(argv)
+    }
+
+    /// Look for a line number of the script within a line of the Task's stderr,
+    /// e.g. `<path>: line 3: …` from bash, `<path>: 3: …` from sh
+    /// or `File "<path>", line 3` from python
+    pub fn scan(&self, output: &str) {
+        let path = self.path.to_string_lossy();
+        let Some((_, rest)) = output.split_once(path.as_ref()) else {
+            return;
+        };
+        let rest = ["\", line ", ": line ", ":"]
+            .iter()
+            .find_map(|prefix| rest.strip_prefix(prefix))
+            .unwrap_or_default()
+            .trim_start();
+        let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+        if let Ok(line) = digits.parse() {
+            *self.reported_line.lock().unwrap() = Some(line);
+        }
+    }
+
+    /// The line that the script failed at, if the shell reported it
+    pub fn failed_line(&self) -> Option<u32> {
+        self.trap_path
+            .as_ref()
+            .and_then(|trap_path| fs::read_to_string(trap_path).ok())
+            .and_then(|line| line.trim().parse().ok())
+            .or(*self.reported_line.lock().unwrap())
+    }
+}
+
+/// Bash code that writes the line the script exited on to `trap_path`,
+/// unless it exited successfully.
+///
+/// The `DEBUG` trap keeps the line of the previous command, as it also runs
+/// for the `EXIT` trap itself, and `set -T` makes it run within functions too.
+fn bash_line_trap(trap_path: &str) -> String {
+    format!(
+        "set -T; rox_exit() {{ [ $? -eq 0 ] || echo \"${{ROX_LINE:-}}\" > {}; }}; \
+         trap '[ \"${{FUNCNAME:-}}\" = rox_exit ] || \
+         {{ ROX_LINE=${{ROX_NEXT_LINE:-}}; ROX_NEXT_LINE=$LINENO; }}' DEBUG; \
+         trap rox_exit EXIT;",
+        trap_path
+    )
+}
+
+/// Shells that take the code to run from the arg after a `-c` anywhere in their flags
+const SH_SHELLS: [&str; 6] = ["sh", "bash", "dash", "ksh", "zsh", "fish"];
+
+/// Long flags that make an interpreter run its next arg as code
+const LONG_CODE_FLAGS: [&str; 3] = ["--eval", "--command", "-Command"];
+
+/// Drop the flag that makes a shell run its next arg as code rather than a file.
+///
+/// The `c` is removed from a trailing group of flags for sh-like shells,
+/// e.g. `bash -ec` becomes `bash -e`, while other interpreters lose a trailing
+/// `c` or `e`, e.g. `python3 -c` or `node -e`, as well as `--eval` & co.
+fn drop_code_flag(argv: &mut Vec<String>) {
+    let [program, .., last] = argv.as_slice() else {
+        return;
+    };
+    if LONG_CODE_FLAGS.contains(&last.as_str()) {
+        argv.pop();
+        return;
+    }
+    let Some(flags) = last
+        .strip_prefix('-')
+        .filter(|flags| !flags.is_empty() && flags.chars().all(|c| c.is_ascii_alphabetic()))
+    else {
+        return;
+    };
+
+    let is_sh = Path::new(program)
+        .file_name()
+        .is_some_and(|name| SH_SHELLS.iter().any(|shell| name == *shell));
+    let remaining = match is_sh {
+        true => flags.replace('c', ""),
+        false => flags.strip_suffix(['c', 'e']).unwrap_or(flags).to_owned(),
+    };
+    if remaining.len() == flags.len() {
+        return;
+    }
+    argv.pop();
+    if !remaining.is_empty() {
+        argv.push(format!("-{}", remaining));
+    }
+}
+
+impl Drop for ScriptFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.dir);
+    }
+}
+
+#[test]
+fn scan_finds_reported_lines() {
+    let task = Task {
+        name: "script".to_owned(),
+        shell: Some(crate::models::Shell::Argv(vec!["python3".to_owned()])),
+        ..Task::default()
+    };
+    let script = ScriptFile::write(&task, "print('hi')\n").unwrap();
+    let path = script.path.to_string_lossy().into_owned();
+    assert_eq!(script.failed_line(), None);
+
+    use std::os::unix::fs::PermissionsExt;
+    let mode = |path: &Path| fs::metadata(path).unwrap().permissions().mode() & 0o777;
+    assert_eq!(mode(&script.dir), 0o700);
+    assert_eq!(mode(&script.path), 0o600);
+
+    script.scan("Traceback (most recent call last):");
+    script.scan(&format!("  File \"{}\", line 3, in <module>", path));
+    assert_eq!(script.failed_line(), Some(3));
+    script.scan(&format!("{}: 7: nope: not found", path));
+    assert_eq!(script.failed_line(), Some(7));
+
+    let dir = script.dir.clone();
+    drop(script);
+    assert!(!Path::new(&path).exists());
+    assert!(!dir.exists());
+}
+
+#[test]
+fn drop_code_flag_handles_combined_flags() {
+    for (shell, expected) in [
+        ("sh -c", "sh"),
+        ("bash -ec", "bash -e"),
+        ("bash -lc", "bash -l"),
+        ("sh -ce", "sh -e"),
+        ("/bin/bash -euo pipefail -c", "/bin/bash -euo pipefail"),
+        ("python3 -c", "python3"),
+        ("python3 -Ic", "python3 -I"),
+        ("node -e", "node"),
+        ("node --eval", "node"),
+        ("perl -we", "perl -w"),
+        ("python3", "python3"),
+        ("bash", "bash"),
+    ] {
+        let mut argv: Vec<String> = shell.split(' ').map(str::to_owned).collect();
+        drop_code_flag(&mut argv);
+        assert_eq!(argv.join(" "), expected);
+    }
+}
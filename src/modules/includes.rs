@@ -0,0 +1,338 @@
+//! Composition of Roxfiles via `include`
+use crate::errors::Diagnostic;
+use crate::models::{DocsKind, Include, RoxFile, ValidationError};
+use crate::modules::execution::model_injection::inject_task_metadata;
+use anyhow::Result;
+use git2::Repository;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+/// Load a Roxfile, recursively merging in any Roxfiles that it includes
+pub fn load_roxfile(file_path: &str) -> Result<RoxFile> {
+    load_with_includes(file_path, &mut IncludeState::default()).map(Option::unwrap_or_default)
+}
+
+/// Parse a Roxfile from its contents, recursively merging in any Roxfiles that it includes.
+///
+/// Relative paths are resolved against the directory of `file_path`,
+/// which is also used to report where problems were found.
+pub fn parse_roxfile(contents: &str, file_path: &str) -> Result<RoxFile> {
+    parse_with_includes(contents, file_path, &mut IncludeState::default())
+}
+
+/// The Roxfiles visited while resolving includes
+#[derive(Default)]
+struct IncludeState {
+    /// The Roxfiles currently being loaded, to catch include cycles
+    stack: Vec<PathBuf>,
+    /// The Roxfiles already merged in, along with the prefixes they were loaded under
+    loaded: HashSet<(PathBuf, Vec<String>)>,
+    /// The prefixes of the includes currently being loaded
+    prefixes: Vec<String>,
+}
+
+/// Returns `None` if the Roxfile was already merged in under the same prefixes,
+/// e.g. when two included Roxfiles both include a common one.
+fn load_with_includes(file_path: &str, state: &mut IncludeState) -> Result<Option<RoxFile>> {
+    let canonical_path = std::fs::canonicalize(file_path)?;
+    if state.stack.contains(&canonical_path) {
+        return Err(ValidationError {
+            message: format!("Roxfile '{}' includes itself!", file_path),
+        }
+        .into());
+    }
+    if !state
+        .loaded
+        .insert((canonical_path.clone(), state.prefixes.clone()))
+    {
+        return Ok(None);
+    }
+
+    let file_string = std::fs::read_to_string(file_path)?;
+    state.stack.push(canonical_path);
+    let roxfile = parse_with_includes(&file_string, file_path, state)?;
+    state.stack.pop();
+    Ok(Some(roxfile))
+}
+
+fn parse_with_includes(
+    contents: &str,
+    file_path: &str,
+    state: &mut IncludeState,
+) -> Result<RoxFile> {
+    let mut roxfile: RoxFile =
+        serde_yaml::from_str(contents).map_err(|e| Diagnostic::from_yaml_error(&e, file_path))?;
+    roxfile.tasks = inject_task_metadata(roxfile.tasks, file_path);
+    set_file_paths(&mut roxfile, file_path);
+    let base_dir = Path::new(file_path).parent().unwrap_or(Path::new(""));
+    resolve_paths(&mut roxfile, base_dir);
+
+    for include in roxfile.include.take().into_iter().flatten() {
+        let (path, prefix) = match include {
+            Include::Path(path) => (path, None),
+            Include::Namespaced { path, prefix } => (path, prefix),
+        };
+
+        let pattern = base_dir.join(&path);
+        let mut included_paths: Vec<PathBuf> = glob::glob(&pattern.to_string_lossy())?
+            .collect::<Result<Vec<PathBuf>, glob::GlobError>>()?;
+        included_paths.sort();
+        if included_paths.is_empty() {
+            return Err(ValidationError {
+                message: format!(
+                    "Included path '{}' in '{}' doesn't match any files!",
+                    path, file_path
+                ),
+            }
+            .into());
+        }
+
+        state.prefixes.extend(prefix.clone());
+        for included_path in included_paths {
+            let Some(mut included) = load_with_includes(&included_path.to_string_lossy(), state)?
+            else {
+                continue;
+            };
+            if let Some(prefix) = &prefix {
+                apply_prefix(&mut included, prefix);
+            }
+            merge_roxfile(&mut roxfile, included)?;
+        }
+        if prefix.is_some() {
+            state.prefixes.pop();
+        }
+    }
+
+    Ok(roxfile)
+}
+
+/// Find the Roxfile by searching upward from the current directory.
+///
+/// The search stops at the root of the git repository, or only
+/// covers the current directory when not within a repository.
+pub fn discover_roxfile(file_name: &str) -> Option<PathBuf> {
+    let current_dir = std::env::current_dir().ok()?;
+    let repo_root = Repository::discover(&current_dir)
+        .ok()
+        .and_then(|repo| repo.workdir().map(Path::to_path_buf))
+        .and_then(|workdir| std::fs::canonicalize(workdir).ok());
+
+    let mut relative_dir = PathBuf::new();
+    for dir in current_dir.ancestors() {
+        if dir.join(file_name).is_file() {
+            return Some(relative_dir.join(file_name));
+        }
+        let is_root = repo_root
+            .as_ref()
+            .is_none_or(|root| std::fs::canonicalize(dir).is_ok_and(|dir| dir == *root));
+        if is_root {
+            break;
+        }
+        relative_dir.push("..");
+    }
+    None
+}
+
+/// Resolve a relative path against the directory of the Roxfile it's defined in
+fn resolve_path(base_dir: &Path, path: &str) -> String {
+    // Collecting the components drops any redundant `.`
+    let resolved: PathBuf = base_dir.join(path).components().collect();
+    resolved.to_string_lossy().into_owned()
+}
+
+/// Make the `workdir`s, `env_file`s and Docs `path`s relative
+/// to the current directory rather than to the Roxfile.
+///
+/// Tasks without a `workdir` run in the directory of their Roxfile.
+fn resolve_paths(roxfile: &mut RoxFile, base_dir: &Path) {
+    let resolve_env_files = |env_files: &mut Option<Vec<String>>| {
+        for env_file in env_files.iter_mut().flatten() {
+            *env_file = resolve_path(base_dir, env_file);
+        }
+    };
+
+    resolve_env_files(&mut roxfile.env_file);
+    for task in roxfile.tasks.iter_mut() {
+        let workdir = task.workdir.as_deref().unwrap_or(".");
+        task.workdir = Some(resolve_path(base_dir, workdir));
+        resolve_env_files(&mut task.env_file);
+    }
+    for pipeline in roxfile.pipelines.iter_mut().flatten() {
+        resolve_env_files(&mut pipeline.env_file);
+    }
+    for doc in roxfile.docs.iter_mut().flatten() {
+        if !matches!(doc.kind, DocsKind::URL) {
+            doc.path = resolve_path(base_dir, &doc.path);
+        }
+    }
+}
+
+/// Record which Roxfile each Pipeline, Template and Doc was defined in
+fn set_file_paths(roxfile: &mut RoxFile, file_path: &str) {
+    for pipeline in roxfile.pipelines.iter_mut().flatten() {
+        pipeline.file_path = Some(file_path.to_owned());
+    }
+    for template in roxfile.templates.iter_mut().flatten() {
+        template.file_path = Some(file_path.to_owned());
+    }
+    for doc in roxfile.docs.iter_mut().flatten() {
+        doc.file_path = Some(file_path.to_owned());
+    }
+}
+
+/// Namespace every name within an included Roxfile, along
+/// with any references to those names.
+pub fn apply_prefix(roxfile: &mut RoxFile, prefix: &str) {
+    let prefixed = |name: &str| format!("{}:{}", prefix, name);
+    let task_names: HashSet<String> = roxfile.tasks.iter().map(|t| t.name.to_owned()).collect();
+    let template_names: HashSet<String> = roxfile
+        .templates
+        .iter()
+        .flatten()
+        .map(|template| template.name.to_owned())
+        .collect();
+    let prefix_task_ref = |name: &mut String| {
+        if task_names.contains(name) {
+            *name = prefixed(name);
+        }
+    };
+
+    for task in roxfile.tasks.iter_mut() {
+        task.name = prefixed(&task.name);
+        task.depends_on
+            .iter_mut()
+            .flatten()
+            .for_each(prefix_task_ref);
+        if let Some(uses) = task.uses.as_mut() {
+            if template_names.contains(uses) {
+                *uses = prefixed(uses);
+            }
+        }
+    }
+    for pipeline in roxfile.pipelines.iter_mut().flatten() {
+        pipeline.name = prefixed(&pipeline.name);
+        pipeline
+            .stages
+            .iter_mut()
+            .flatten()
+            .for_each(prefix_task_ref);
+    }
+    for template in roxfile.templates.iter_mut().flatten() {
+        template.name = prefixed(&template.name);
+    }
+    for doc in roxfile.docs.iter_mut().flatten() {
+        doc.name = prefixed(&doc.name);
+    }
+}
+
+/// Raise an error if a name is already in use
+fn check_collision<'a>(
+    kind: &str,
+    existing: impl Iterator<Item = &'a String>,
+    added: impl Iterator<Item = &'a String>,
+) -> Result<(), ValidationError> {
+    let existing: HashSet<&String> = existing.collect();
+    for name in added {
+        if existing.contains(name) {
+            return Err(ValidationError {
+                message: format!(
+                    "{} '{}' is defined in multiple Roxfiles! Consider using a 'prefix' for the include.",
+                    kind, name
+                ),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Merge the tasks, templates, pipelines and docs of an included Roxfile.
+///
+/// Top-level settings such as `ci` and `env` are only read from the root Roxfile.
+fn merge_roxfile(roxfile: &mut RoxFile, included: RoxFile) -> Result<(), ValidationError> {
+    check_collision(
+        "Task",
+        roxfile.tasks.iter().map(|task| &task.name),
+        included.tasks.iter().map(|task| &task.name),
+    )?;
+    check_collision(
+        "Pipeline",
+        roxfile.pipelines.iter().flatten().map(|p| &p.name),
+        included.pipelines.iter().flatten().map(|p| &p.name),
+    )?;
+    check_collision(
+        "Template",
+        roxfile.templates.iter().flatten().map(|t| &t.name),
+        included.templates.iter().flatten().map(|t| &t.name),
+    )?;
+    check_collision(
+        "Docs",
+        roxfile.docs.iter().flatten().map(|d| &d.name),
+        included.docs.iter().flatten().map(|d| &d.name),
+    )?;
+
+    roxfile.tasks.extend(included.tasks);
+    if let Some(pipelines) = included.pipelines {
+        roxfile
+            .pipelines
+            .get_or_insert_with(Vec::new)
+            .extend(pipelines);
+    }
+    if let Some(templates) = included.templates {
+        roxfile
+            .templates
+            .get_or_insert_with(Vec::new)
+            .extend(templates);
+    }
+    if let Some(docs) = included.docs {
+        roxfile.docs.get_or_insert_with(Vec::new).extend(docs);
+    }
+    Ok(())
+}
+
+#[test]
+fn apply_prefix_updates_references() {
+    use crate::models::{Pipeline, Task};
+
+    let mut roxfile = RoxFile {
+        tasks: vec![
+            Task {
+                name: "build".to_owned(),
+                command: Some("cargo build".to_owned()),
+                ..Default::default()
+            },
+            Task {
+                name: "test".to_owned(),
+                command: Some("cargo test".to_owned()),
+                depends_on: Some(vec!["build".to_owned(), "external".to_owned()]),
+                ..Default::default()
+            },
+        ],
+        pipelines: Some(vec![Pipeline {
+            name: "ci".to_owned(),
+            stages: vec![vec!["test".to_owned()]],
+            ..Default::default()
+        }]),
+        ..Default::default()
+    };
+    apply_prefix(&mut roxfile, "foo");
+
+    assert_eq!(roxfile.tasks[0].name, "foo:build");
+    assert_eq!(
+        roxfile.tasks[1].depends_on,
+        Some(vec!["foo:build".to_owned(), "external".to_owned()])
+    );
+    let pipeline = &roxfile.pipelines.unwrap()[0];
+    assert_eq!(pipeline.name, "foo:ci");
+    assert_eq!(pipeline.stages, vec![vec!["foo:test".to_owned()]]);
+}
+
+#[test]
+fn resolve_path_is_relative_to_roxfile() {
+    assert_eq!(resolve_path(Path::new(""), "src"), "src");
+    assert_eq!(resolve_path(Path::new("crates/foo"), "."), "crates/foo");
+    assert_eq!(
+        resolve_path(Path::new("crates/foo"), "./src"),
+        "crates/foo/src"
+    );
+    assert_eq!(resolve_path(Path::new("crates/foo"), "/tmp"), "/tmp");
+}
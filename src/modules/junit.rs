@@ -0,0 +1,188 @@
+//! Conversion of execution results into JUnit XML reports
+use crate::models::{JobResults, PassFail, TaskResult};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// Escape a string for use within XML text or attributes.
+///
+/// Characters that aren't allowed in XML, such as
+/// terminal escape codes, are removed entirely.
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\t' | '\n' | '\r' => escaped.push(c),
+            c if c < ' ' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+fn count_failures(results: &[&TaskResult]) -> usize {
+    results.iter().filter(|result| result.is_failure()).count()
+}
+
+fn count_skipped(results: &[&TaskResult]) -> usize {
+    results
+        .iter()
+        .filter(|result| {
+            matches!(result.result, PassFail::Cached | PassFail::NotRun)
+                || (result.allow_failure && result.result.is_failure())
+        })
+        .count()
+}
+
+/// Build the element describing how a Task didn't pass, if it didn't
+fn outcome_element(result: &TaskResult) -> Option<String> {
+    let mut exit_code = result
+        .exit_code
+        .map(|code| format!("Exited with code {}", code))
+        .unwrap_or("The command didn't exit".to_owned());
+    if let Some(line) = result.failed_line {
+        exit_code = format!("{} at line {} of the script", exit_code, line);
+    }
+
+    let element = match result.result {
+        PassFail::Pass => return None,
+        PassFail::Cached => "<skipped message=\"Inputs unchanged\"/>".to_owned(),
+        PassFail::NotRun => "<skipped message=\"Not run\"/>".to_owned(),
+        _ if result.allow_failure => format!(
+            "<skipped message=\"{}\"/>",
+            escape_xml(&format!("{} (allowed): {}", result.result, exit_code))
+        ),
+        PassFail::Fail => format!(
+            "<failure message=\"{}\" type=\"Fail\">{}</failure>",
+            escape_xml(&exit_code),
+            escape_xml(&result.command)
+        ),
+        PassFail::TimedOut => format!(
+            "<failure message=\"Timed out\" type=\"TimedOut\">{}</failure>",
+            escape_xml(&result.command)
+        ),
+        PassFail::Cancelled => format!(
+            "<error message=\"Cancelled\" type=\"Cancelled\">{}</error>",
+            escape_xml(&result.command)
+        ),
+    };
+    Some(element)
+}
+
+fn write_testcase(xml: &mut String, job_name: &str, result: &TaskResult) {
+    let _ = writeln!(
+        xml,
+        "    <testcase name=\"{}\" classname=\"{}\" time=\"{}\">",
+        escape_xml(&result.name),
+        escape_xml(job_name),
+        result.elapsed_time
+    );
+    if let Some(element) = outcome_element(result) {
+        let _ = writeln!(xml, "      {}", element);
+    }
+    if let Some(output) = result
+        .log_path
+        .as_ref()
+        .and_then(|log_path| std::fs::read_to_string(log_path).ok())
+    {
+        let _ = writeln!(
+            xml,
+            "      <system-out>{}</system-out>",
+            escape_xml(&output)
+        );
+    }
+    let _ = writeln!(xml, "    </testcase>");
+}
+
+/// Convert execution results into a JUnit XML report.
+///
+/// Each stage of each job becomes a testsuite,
+/// with a testcase for each of its Tasks.
+pub fn to_junit_xml(jobs: &[JobResults]) -> String {
+    let all_results: Vec<&TaskResult> = jobs.iter().flat_map(|job| &job.results).collect();
+    let total_time: i64 = all_results.iter().map(|result| result.elapsed_time).sum();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    let _ = writeln!(
+        xml,
+        "<testsuites name=\"rox\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\">",
+        all_results.len(),
+        count_failures(&all_results),
+        count_skipped(&all_results),
+        total_time
+    );
+
+    for job in jobs {
+        let mut stages: BTreeMap<i8, Vec<&TaskResult>> = BTreeMap::new();
+        for result in job.results.iter() {
+            stages.entry(result.stage).or_default().push(result);
+        }
+
+        for (stage, results) in stages {
+            let _ = writeln!(
+                xml,
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{}\" timestamp=\"{}\">",
+                escape_xml(&format!("{} - Stage {}", job.job_name, stage)),
+                results.len(),
+                count_failures(&results),
+                count_skipped(&results),
+                results.iter().map(|result| result.elapsed_time).sum::<i64>(),
+                escape_xml(&job.execution_time)
+            );
+            for result in results {
+                write_testcase(&mut xml, &job.job_name, result);
+            }
+            let _ = writeln!(xml, "  </testsuite>");
+        }
+    }
+
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+/// Write a JUnit XML report of the execution results to a file
+pub fn write_junit_report(jobs: &[JobResults], path: &str) -> std::io::Result<()> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, to_junit_xml(jobs))
+}
+
+#[test]
+fn junit_groups_stages_and_escapes() {
+    let result = |name: &str, stage: i8, result: PassFail| TaskResult {
+        name: name.to_owned(),
+        command: "test \"a\" < b".to_owned(),
+        stage,
+        result,
+        elapsed_time: 1,
+        file_path: "roxfile.yml".to_owned(),
+        log_path: None,
+        attempts: 1,
+        allow_failure: false,
+        exit_code: Some(1),
+        failed_line: None,
+    };
+    let job = JobResults {
+        job_name: "ci".to_owned(),
+        execution_time: "now".to_owned(),
+        results: vec![
+            result("build", 1, PassFail::Pass),
+            result("test", 2, PassFail::Fail),
+            result("deploy", 3, PassFail::NotRun),
+        ],
+    };
+    let xml = to_junit_xml(&[job]);
+
+    assert!(xml.contains("tests=\"3\" failures=\"1\" skipped=\"1\""));
+    assert_eq!(xml.matches("<testsuite ").count(), 3);
+    assert!(xml.contains("<testsuite name=\"ci - Stage 2\""));
+    assert!(xml.contains(
+        "<failure message=\"Exited with code 1\" type=\"Fail\">test &quot;a&quot; &lt; b</failure>"
+    ));
+    assert!(xml.contains("<skipped message=\"Not run\"/>"));
+}
@@ -0,0 +1,75 @@
+//! Listing of everything that is defined within a Roxfile
+use crate::models::RoxFile;
+use crate::modules::execution::output::{print_json, print_json_line, OutputFormat};
+use serde_json::json;
+
+/// Display the Tasks, Pipelines, Templates and Docs of a Roxfile
+pub fn display_list(roxfile: &RoxFile, output: OutputFormat) {
+    let listing = json!({
+        "tasks": roxfile.tasks,
+        "pipelines": roxfile.pipelines.as_deref().unwrap_or_default(),
+        "templates": roxfile.templates.as_deref().unwrap_or_default(),
+        "docs": roxfile.docs.as_deref().unwrap_or_default(),
+    });
+
+    match output {
+        OutputFormat::Json => print_json(&listing),
+        OutputFormat::Ndjson => print_json_line(&listing),
+        OutputFormat::Text => {
+            print_section(
+                "Tasks",
+                roxfile
+                    .tasks
+                    .iter()
+                    .filter(|task| !task.hide.unwrap_or_default())
+                    .map(|task| (&task.name, task.description.as_ref()))
+                    .collect(),
+            );
+            print_section(
+                "Pipelines",
+                roxfile
+                    .pipelines
+                    .iter()
+                    .flatten()
+                    .map(|pipeline| (&pipeline.name, pipeline.description.as_ref()))
+                    .collect(),
+            );
+            print_section(
+                "Templates",
+                roxfile
+                    .templates
+                    .iter()
+                    .flatten()
+                    .map(|template| (&template.name, Some(&template.command)))
+                    .collect(),
+            );
+            print_section(
+                "Docs",
+                roxfile
+                    .docs
+                    .iter()
+                    .flatten()
+                    .map(|doc| (&doc.name, doc.description.as_ref()))
+                    .collect(),
+            );
+        }
+    }
+}
+
+/// Print the names and descriptions of a single kind of item, aligned
+fn print_section(title: &str, entries: Vec<(&String, Option<&String>)>) {
+    if entries.is_empty() {
+        return;
+    }
+    let width = entries.iter().map(|(name, _)| name.len()).max().unwrap();
+
+    println!("{}:", title);
+    for (name, description) in entries {
+        println!(
+            "  {:width$}  {}",
+            name,
+            description.map(String::as_str).unwrap_or_default(),
+            width = width
+        );
+    }
+}
@@ -1,43 +1,183 @@
+use crate::errors::RoxError;
 use crate::models::JobResults;
-use crate::modules::execution::output::display_execution_results;
+use crate::modules::docs::run_app;
+use crate::modules::execution::output::{
+    display_execution_results, print_json, print_json_line, OutputFormat,
+};
+use crate::modules::junit::to_junit_xml;
+use crate::utils::sanitize_filename;
+use serde_json::json;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
-const LOG_DIR: &str = ".rox";
+pub const LOG_DIR: &str = ".rox";
+static LOG_ROOT: OnceLock<PathBuf> = OnceLock::new();
 
-/// Load execution results from a log file
-pub fn display_logs(number: &i8) {
-    let mut filenames = std::fs::read_dir(LOG_DIR)
-        .unwrap()
-        .map(|res| res.map(|e| e.path()))
-        .collect::<Result<Vec<_>, std::io::Error>>()
-        .unwrap();
+/// Keep the logs next to the Roxfile, wherever Rox is run from
+pub fn set_log_root(roxfile_path: &str) {
+    let dir = Path::new(roxfile_path).parent().unwrap_or(Path::new(""));
+    let dir = std::fs::canonicalize(dir).unwrap_or(dir.to_path_buf());
+    let _ = LOG_ROOT.set(dir);
+}
+
+/// Get the directory that the execution logs are written to
+pub fn log_dir() -> PathBuf {
+    LOG_ROOT
+        .get()
+        .map_or(PathBuf::from(LOG_DIR), |root| root.join(LOG_DIR))
+}
+
+fn output_dir() -> PathBuf {
+    log_dir().join("output")
+}
+
+/// Load the most recent execution results from the log files, oldest first
+fn load_logs(number: &i8) -> Result<Vec<JobResults>, RoxError> {
+    let log_dir = log_dir();
+    let mut filenames = std::fs::read_dir(&log_dir)
+        .and_then(|entries| {
+            entries
+                .map(|res| res.map(|e| e.path()))
+                .collect::<Result<Vec<_>, std::io::Error>>()
+        })
+        .map_err(|e| RoxError::log_io(log_dir.to_string_lossy(), e))?;
+    filenames.retain(|path| path.is_file() && path.to_string_lossy().ends_with(".log.yaml"));
     filenames.sort();
 
-    let results: Vec<JobResults> = filenames
+    let mut results: Vec<JobResults> = filenames
         .iter()
         .rev()
         .take(*number as usize)
         .map(|filename| {
-            let contents = std::fs::read_to_string(filename).unwrap();
-            serde_yaml::from_str(&contents).unwrap()
+            let log_io = |e| RoxError::log_io(filename.to_string_lossy(), e);
+            let contents = std::fs::read_to_string(filename).map_err(log_io)?;
+            serde_yaml::from_str(&contents)
+                .map_err(|e| log_io(std::io::Error::new(std::io::ErrorKind::InvalidData, e)))
         })
-        .collect();
+        .collect::<Result<Vec<JobResults>, RoxError>>()?;
+    results.reverse();
+    Ok(results)
+}
+
+/// Load execution results from a log file
+pub fn display_logs(number: &i8, output: OutputFormat) -> Result<(), RoxError> {
+    let logs = load_logs(number)?;
+    match output {
+        OutputFormat::Json => print_json(&logs),
+        OutputFormat::Ndjson => logs.iter().for_each(print_json_line),
+        OutputFormat::Text => {
+            for result in logs.iter() {
+                println!("\n> {} | {}", result.job_name, result.execution_time);
+                display_execution_results(result)
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the most recent execution results as a JUnit XML report
+pub fn export_junit(number: &i8) -> Result<(), RoxError> {
+    print!("{}", to_junit_xml(&load_logs(number)?));
+    Ok(())
+}
+
+/// Display the captured output of a Task from the most recent log files
+pub fn display_task_output(
+    number: &i8,
+    task_name: &str,
+    page: bool,
+    output: OutputFormat,
+) -> Result<(), RoxError> {
+    let mut outputs: Vec<(String, String)> = Vec::new();
+    let mut entries: Vec<serde_json::Value> = Vec::new();
+
+    for job in load_logs(number)?.iter() {
+        for result in job.results.iter().filter(|result| result.name == task_name) {
+            let Some(log_path) = &result.log_path else {
+                continue;
+            };
+            let header = format!(
+                "{} | {} | {} | {}",
+                job.job_name, job.execution_time, result.name, result.result
+            );
+            let contents = std::fs::read_to_string(log_path)
+                .unwrap_or_else(|_| format!("Output file '{}' no longer exists!", log_path));
+            entries.push(json!({
+                "job_name": job.job_name,
+                "execution_time": job.execution_time,
+                "result": result,
+                "output": contents,
+            }));
+            outputs.push((header, contents));
+        }
+    }
 
-    for result in results.iter().rev() {
-        println!("\n> {} | {}", result.job_name, result.execution_time);
-        display_execution_results(result)
+    match output {
+        OutputFormat::Json => print_json(&entries),
+        OutputFormat::Ndjson => entries.iter().for_each(print_json_line),
+        OutputFormat::Text if page && !outputs.is_empty() => {
+            let markdown = outputs
+                .iter()
+                .map(|(header, contents)| format!("## {}\n```\n{}\n```\n", header, contents))
+                .collect::<Vec<String>>()
+                .join("\n");
+            run_app(&markdown).map_err(|e| {
+                RoxError::log_io(
+                    output_dir().to_string_lossy(),
+                    std::io::Error::other(e.to_string()),
+                )
+            })?;
+        }
+        OutputFormat::Text if outputs.is_empty() => {
+            println!("> No captured output found for task '{}'", task_name)
+        }
+        OutputFormat::Text => {
+            for (header, contents) in outputs {
+                println!("\n> {}", header);
+                print!("{}", contents);
+            }
+        }
     }
+    Ok(())
+}
+
+/// Get the name of the log file that the execution results are written to
+pub fn log_file_name(results: &JobResults) -> String {
+    format!("rox-{}.log.yaml", results.execution_time)
 }
 
 /// Write the execution results to a log file
-pub fn write_logs(results: &JobResults) -> String {
-    let filename = format!("rox-{}.log.yaml", results.execution_time);
-    let filepath = format!("{}/{}", LOG_DIR, filename);
+pub fn write_logs(results: &JobResults) -> Result<String, RoxError> {
+    let filename = log_file_name(results);
+    let log_dir = log_dir();
+    let filepath = log_dir.join(&filename).to_string_lossy().into_owned();
 
     // Make sure the log directory exists
-    if !std::path::Path::new(LOG_DIR).exists() {
-        std::fs::create_dir(LOG_DIR).unwrap();
-    }
+    std::fs::create_dir_all(&log_dir)
+        .map_err(|e| RoxError::log_io(log_dir.to_string_lossy(), e))?;
+
+    let contents = serde_yaml::to_string(results)
+        .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e));
+    contents
+        .and_then(|contents| std::fs::write(&filepath, contents))
+        .map_err(|e| RoxError::log_io(filepath, e))?;
+    Ok(filename)
+}
+
+/// Create the file used to capture a Task's output
+pub fn create_output_file(task_name: &str) -> std::io::Result<(String, File)> {
+    let output_dir = output_dir();
+    let filepath = output_dir
+        .join(format!(
+            "{}-{}.log",
+            chrono::Utc::now().to_rfc3339(),
+            sanitize_filename(task_name)
+        ))
+        .to_string_lossy()
+        .into_owned();
 
-    std::fs::write(filepath, serde_yaml::to_string(results).unwrap()).unwrap();
-    filename
+    std::fs::create_dir_all(&output_dir)?;
+    let file = File::create(&filepath)?;
+    Ok((filepath, file))
 }
@@ -1,4 +1,12 @@
 pub mod ci;
+pub mod completions;
 pub mod docs;
 pub mod execution;
+pub mod includes;
+pub mod junit;
+pub mod list;
 pub mod logs;
+pub mod picker;
+pub mod schema;
+pub mod validation;
+pub mod watch;
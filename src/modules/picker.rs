@@ -0,0 +1,263 @@
+//! Interactive fuzzy finder for choosing a Task or Pipeline to run
+use crate::models::RoxFile;
+use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use crossterm::execute;
+use crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::backend::CrosstermBackend;
+use ratatui::layout::{Constraint, Direction, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Cell, Paragraph, Row, Table, TableState};
+use ratatui::{Frame, Terminal};
+use std::io::{stdout, IsTerminal};
+
+const KEYBINDINGS: &str = " Type to search | Up/Down: Select | Enter: Run | Esc: Cancel";
+
+/// Something that can be picked and run
+#[derive(Debug, Clone, PartialEq)]
+pub struct Entry {
+    /// The subcommand that runs the entry, i.e. `task` or `pl`
+    pub subcommand: &'static str,
+    pub name: String,
+    description: String,
+    command: String,
+    file_path: String,
+}
+
+impl Entry {
+    fn kind(&self) -> &'static str {
+        match self.subcommand {
+            "pl" => "Pipeline",
+            _ => "Task",
+        }
+    }
+}
+
+/// Get every visible Task & Pipeline within the Roxfile
+pub fn build_entries(roxfile: &RoxFile, file_path: &str) -> Vec<Entry> {
+    let tasks = roxfile
+        .tasks
+        .iter()
+        .filter(|task| !task.hide.unwrap_or_default())
+        .map(|task| Entry {
+            subcommand: "task",
+            name: task.name.to_owned(),
+            description: task.description.clone().unwrap_or_default(),
+            command: task
+                .command
+                .clone()
+                .or_else(|| task.script.as_deref().map(script_summary))
+                .unwrap_or_default(),
+            file_path: task.file_path.clone().unwrap_or(file_path.to_owned()),
+        });
+    let pipelines = roxfile.pipelines.iter().flatten().map(|pipeline| Entry {
+        subcommand: "pl",
+        name: pipeline.name.to_owned(),
+        description: pipeline.description.clone().unwrap_or_default(),
+        command: pipeline
+            .stages
+            .iter()
+            .enumerate()
+            .map(|(i, stage)| format!("Stage {}: {}", i + 1, stage.join(", ")))
+            .collect::<Vec<String>>()
+            .join(" | "),
+        file_path: pipeline.file_path.clone().unwrap_or(file_path.to_owned()),
+    });
+    tasks.chain(pipelines).collect()
+}
+
+/// Show a script on a single line, as its first line
+fn script_summary(script: &str) -> String {
+    let mut lines = script.trim().lines();
+    let first = lines.next().unwrap_or_default();
+    match lines.next() {
+        Some(_) => format!("{} …", first),
+        None => first.to_owned(),
+    }
+}
+
+/// Score how well a query fuzzily matches some text, if at all.
+///
+/// Every character of the query has to appear in the text in order, with
+/// consecutive characters and characters at the start of words scoring higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i64> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars() {
+        let offset = text[position..].iter().position(|c| *c == query_char)?;
+        let index = position + offset;
+        score += 1;
+        if previous_match.is_some_and(|previous| previous + 1 == index) {
+            score += 5;
+        }
+        if index == 0 || !text[index - 1].is_alphanumeric() {
+            score += 3;
+        }
+        previous_match = Some(index);
+        position = index + 1;
+    }
+    // Prefer shorter names when matches are otherwise equal
+    Some(score * 100 - text.len() as i64)
+}
+
+/// Get the entries that match the query, best match first
+fn filter_entries<'a>(entries: &'a [Entry], query: &str) -> Vec<&'a Entry> {
+    let mut matches: Vec<(i64, &Entry)> = entries
+        .iter()
+        .filter_map(|entry| fuzzy_score(query, &entry.name).map(|score| (score, entry)))
+        .collect();
+    // The sort is stable, so entries with equal scores stay in the Roxfile's order
+    matches.sort_by_key(|(score, _)| -score);
+    matches.into_iter().map(|(_, entry)| entry).collect()
+}
+
+/// Whether the picker can be shown, which requires an interactive terminal
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && stdout().is_terminal()
+}
+
+/// Let the user pick an entry, returning `None` if they cancel
+pub fn pick(entries: &[Entry]) -> std::io::Result<Option<Entry>> {
+    enable_raw_mode()?;
+    execute!(stdout(), EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout()))?;
+
+    let mut query = String::new();
+    let mut table_state = TableState::default().with_selected(Some(0));
+    let result = (|| -> std::io::Result<Option<Entry>> {
+        loop {
+            let matches = filter_entries(entries, &query);
+            if table_state.selected().unwrap_or_default() >= matches.len() {
+                table_state.select(Some(matches.len().saturating_sub(1)));
+            }
+            terminal.draw(|frame| render(frame, &query, &matches, &mut table_state))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let selected = table_state.selected().unwrap_or_default();
+            let control = key.modifiers.contains(KeyModifiers::CONTROL);
+            match key.code {
+                KeyCode::Esc => return Ok(None),
+                KeyCode::Char('c') if control => return Ok(None),
+                KeyCode::Enter => return Ok(matches.get(selected).map(|entry| (*entry).clone())),
+                KeyCode::Up => table_state.select(Some(selected.saturating_sub(1))),
+                KeyCode::Char('p') if control => {
+                    table_state.select(Some(selected.saturating_sub(1)))
+                }
+                KeyCode::Down => table_state.select(Some(selected + 1)),
+                KeyCode::Char('n') if control => table_state.select(Some(selected + 1)),
+                KeyCode::Backspace => {
+                    query.pop();
+                    table_state.select(Some(0));
+                }
+                KeyCode::Char(c) if !control => {
+                    query.push(c);
+                    table_state.select(Some(0));
+                }
+                _ => {}
+            }
+        }
+    })();
+
+    disable_raw_mode()?;
+    execute!(stdout(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn render(frame: &mut Frame, query: &str, matches: &[&Entry], table_state: &mut TableState) {
+    let sections = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(5),
+            Constraint::Length(1),
+        ])
+        .split(frame.size());
+
+    let search = Paragraph::new(format!("> {}", query))
+        .block(Block::default().borders(Borders::ALL).title(" Search "));
+    frame.render_widget(search, sections[0]);
+
+    let rows: Vec<Row> = matches
+        .iter()
+        .map(|entry| {
+            Row::new(vec![
+                Cell::from(entry.kind()).style(Style::default().fg(Color::Cyan)),
+                Cell::from(entry.name.to_owned()),
+                Cell::from(entry.description.to_owned())
+                    .style(Style::default().fg(Color::DarkGray)),
+            ])
+        })
+        .collect();
+    let table = Table::new(
+        rows,
+        [
+            Constraint::Length(8),
+            Constraint::Percentage(35),
+            Constraint::Percentage(65),
+        ],
+    )
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .title(format!(" Tasks & Pipelines ({}) ", matches.len())),
+    )
+    .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    frame.render_stateful_widget(table, sections[1], table_state);
+
+    let details: Vec<Line> = match table_state.selected().and_then(|i| matches.get(i)) {
+        Some(entry) => vec![
+            Line::from(format!("Description: {}", entry.description)),
+            Line::from(format!("Command: {}", entry.command)),
+            Line::from(format!("File: {}", entry.file_path)),
+        ],
+        None => vec![Line::from("No matches")],
+    };
+    let details =
+        Paragraph::new(details).block(Block::default().borders(Borders::ALL).title(" Details "));
+    frame.render_widget(details, sections[2]);
+
+    frame.render_widget(
+        Paragraph::new(KEYBINDINGS).style(Style::default().fg(Color::DarkGray)),
+        sections[3],
+    );
+}
+
+#[test]
+fn filter_entries_ranks_fuzzy_matches() {
+    let entry = |name: &str| Entry {
+        subcommand: "task",
+        name: name.to_owned(),
+        description: String::new(),
+        command: String::new(),
+        file_path: "roxfile.yml".to_owned(),
+    };
+    let entries = vec![
+        entry("docker-build"),
+        entry("build"),
+        entry("bench"),
+        entry("lint"),
+    ];
+
+    let names = |query: &str| -> Vec<String> {
+        filter_entries(&entries, query)
+            .into_iter()
+            .map(|entry| entry.name.to_owned())
+            .collect()
+    };
+    assert_eq!(names("bld"), vec!["build", "docker-build"]);
+    assert_eq!(names("BE"), vec!["bench"]);
+    assert_eq!(names("").len(), 4);
+    assert!(names("xyz").is_empty());
+}
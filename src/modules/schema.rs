@@ -0,0 +1,40 @@
+//! JSON Schema of the Roxfile, for editor validation & autocompletion
+use crate::models::RoxFile;
+use crate::modules::execution::output::print_json;
+use schemars::schema::RootSchema;
+use schemars::schema_for;
+
+/// Generate the JSON Schema of the Roxfile from its structs
+pub fn roxfile_schema() -> RootSchema {
+    let mut schema = schema_for!(RoxFile);
+    let metadata = schema.schema.metadata();
+    metadata.title = Some("Roxfile".to_owned());
+    metadata.description = Some("The configuration file for the Rox CLI".to_owned());
+    schema
+}
+
+/// Print the JSON Schema of the Roxfile
+pub fn display_schema() {
+    print_json(&roxfile_schema());
+}
+
+#[test]
+fn schema_describes_roxfile() {
+    let schema = serde_json::to_value(roxfile_schema()).unwrap();
+
+    assert_eq!(schema["additionalProperties"], false);
+    assert_eq!(
+        schema["definitions"]["Task"]["properties"]["workdir"]["description"],
+        "The directory to run the command in, defaulting to the Roxfile's directory"
+    );
+    let kinds: Vec<&str> = schema["definitions"]["DocsKind"]["oneOf"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|kind| kind["enum"].as_array().unwrap())
+        .map(|kind| kind.as_str().unwrap())
+        .collect();
+    assert_eq!(kinds, vec!["markdown", "text", "url"]);
+    // Fields that are only set at runtime aren't part of the schema
+    assert!(schema["definitions"]["Task"]["properties"]["args"].is_null());
+}
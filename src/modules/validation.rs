@@ -0,0 +1,632 @@
+//! Validation of an entire Roxfile, reporting every problem along with where it is
+use crate::cli::RESERVED_PARAM_NAMES;
+use crate::errors::{Diagnostic, RoxError};
+use crate::models::{DocsKind, RoxFile, Shell, Task, Validate};
+use crate::modules::execution::dependencies::find_dependency_cycle;
+use crate::modules::execution::matrix::{expand_matrix, expand_matrix_tasks};
+use crate::modules::execution::output::{print_json, print_json_line, OutputFormat};
+use crate::modules::includes::load_roxfile;
+use crate::utils::split_words;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// The top-level sections of a Roxfile that contain named items
+#[derive(Clone, Copy)]
+enum Section {
+    Tasks,
+    Pipelines,
+    Templates,
+    Docs,
+}
+
+impl Section {
+    fn key(&self) -> &'static str {
+        match self {
+            Section::Tasks => "tasks",
+            Section::Pipelines => "pipelines",
+            Section::Templates => "templates",
+            Section::Docs => "docs",
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Section::Tasks => "Task",
+            Section::Pipelines => "Pipeline",
+            Section::Templates => "Template",
+            Section::Docs => "Docs",
+        }
+    }
+}
+
+/// The part of an item that a problem should point to
+enum Field<'a> {
+    Name,
+    Key(&'a str),
+    /// A value within the block of a key, e.g. a single entry of a list
+    Value(&'a str, &'a str),
+}
+
+/// A reference to a single item within the Roxfile
+struct Item<'a> {
+    section: Section,
+    name: &'a str,
+    file_path: &'a str,
+    /// Which definition of the name within its file, for duplicates
+    occurrence: usize,
+}
+
+/// Number of leading spaces on a line
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+/// Whether a line contains nothing that YAML would parse
+fn is_blank(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.is_empty() || trimmed.starts_with('#')
+}
+
+/// Get the column of a key if it's defined on this line, skipping a list marker
+fn key_column(line: &str, key: &str) -> Option<usize> {
+    let indent = indentation(line);
+    let mut rest = &line[indent..];
+    let mut column = indent;
+    if let Some(stripped) = rest.strip_prefix("- ") {
+        let item_indent = indentation(stripped);
+        column += 2 + item_indent;
+        rest = &stripped[item_indent..];
+    }
+    rest.strip_prefix(key)
+        .is_some_and(|rest| rest.starts_with(':'))
+        .then_some(column)
+}
+
+/// Find a value on a line that isn't part of a longer name
+fn value_column(line: &str, value: &str) -> Option<usize> {
+    let is_name_char = |c: char| c.is_alphanumeric() || "_-:.".contains(c);
+    line.match_indices(value)
+        .map(|(index, _)| index)
+        .find(|index| {
+            let before = line[..*index].chars().next_back();
+            let after = line[index + value.len()..].chars().next();
+            !before.is_some_and(is_name_char) && !after.is_some_and(is_name_char)
+        })
+}
+
+/// The lines of each Roxfile, used to point problems at their location
+#[derive(Default)]
+struct SourceMap {
+    files: HashMap<String, Vec<String>>,
+}
+
+impl SourceMap {
+    fn lines(&mut self, file_path: &str) -> &[String] {
+        self.files.entry(file_path.to_owned()).or_insert_with(|| {
+            std::fs::read_to_string(file_path)
+                .map(|source| source.lines().map(str::to_owned).collect())
+                .unwrap_or_default()
+        })
+    }
+
+    /// Find the 1-based line & column of a field of an item
+    fn locate(&mut self, item: &Item, field: &Field) -> Option<(usize, usize)> {
+        let lines = self.lines(item.file_path);
+
+        // The section runs until the next top-level key
+        let section_start = lines
+            .iter()
+            .position(|line| key_column(line, item.section.key()) == Some(0))?;
+        let section_end = lines[section_start + 1..]
+            .iter()
+            .position(|line| !is_blank(line) && indentation(line) == 0 && !line.starts_with('-'))
+            .map_or(lines.len(), |offset| section_start + 1 + offset);
+        let section = section_start + 1..section_end;
+
+        // Included names may have been prefixed, so also try without the prefixes
+        let candidates: Vec<&str> = std::iter::once(item.name)
+            .chain(
+                item.name
+                    .match_indices(':')
+                    .map(|(i, _)| &item.name[i + 1..]),
+            )
+            .collect();
+        let (name_line, name_column) = candidates.iter().find_map(|name| {
+            section
+                .clone()
+                .filter_map(|index| {
+                    let column = key_column(&lines[index], "name")?;
+                    let value = lines[index][column + "name:".len()..].trim();
+                    let value = value.trim_matches(|c| c == '"' || c == '\'');
+                    (value == *name).then_some((index, column))
+                })
+                .nth(item.occurrence)
+        })?;
+
+        // The item's fields are indented at least as far as its name
+        let item_end = lines[name_line + 1..section_end]
+            .iter()
+            .position(|line| !is_blank(line) && indentation(line) < name_column)
+            .map_or(section_end, |offset| name_line + 1 + offset);
+        let find_key = |key: &str| {
+            (name_line..item_end).find_map(|index| {
+                key_column(&lines[index], key)
+                    .filter(|column| *column == name_column)
+                    .map(|column| (index, column))
+            })
+        };
+
+        let (line, column) = match field {
+            Field::Name => (name_line, name_column),
+            Field::Key(key) => find_key(key).unwrap_or((name_line, name_column)),
+            Field::Value(key, value) => match find_key(key) {
+                Some((key_line, key_col)) => (key_line..item_end)
+                    .find_map(|index| value_column(&lines[index], value).map(|c| (index, c)))
+                    .unwrap_or((key_line, key_col)),
+                None => (name_line, name_column),
+            },
+        };
+        Some((line + 1, column + 1))
+    }
+}
+
+/// Collects the problems within a Roxfile
+struct Validator {
+    sources: SourceMap,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Validator {
+    fn report(&mut self, item: &Item, field: Field, message: String) {
+        let location = self.sources.locate(item, &field);
+        self.diagnostics.push(Diagnostic {
+            message,
+            file_path: item.file_path.to_owned(),
+            line: location.map(|(line, _)| line),
+            column: location.map(|(_, column)| column),
+        });
+    }
+
+    /// Report a problem with a top-level key of the Roxfile
+    fn report_top_level(&mut self, file_path: &str, key: &str, message: String) {
+        let line = self
+            .sources
+            .lines(file_path)
+            .iter()
+            .position(|line| key_column(line, key) == Some(0));
+        self.diagnostics.push(Diagnostic {
+            message,
+            file_path: file_path.to_owned(),
+            line: line.map(|line| line + 1),
+            column: line.map(|_| 1),
+        });
+    }
+
+    /// Report names that are defined more than once, returning each item
+    fn check_duplicates<'a>(
+        &mut self,
+        section: Section,
+        items: impl Iterator<Item = (&'a str, &'a str)>,
+    ) -> Vec<Item<'a>> {
+        let mut occurrences: HashMap<(&str, &str), usize> = HashMap::new();
+        let items: Vec<Item> = items
+            .map(|(name, file_path)| {
+                let occurrence = occurrences.entry((name, file_path)).or_default();
+                *occurrence += 1;
+                Item {
+                    section,
+                    name,
+                    file_path,
+                    occurrence: *occurrence - 1,
+                }
+            })
+            .collect();
+
+        let mut seen = HashSet::new();
+        for item in items.iter() {
+            if !seen.insert(item.name) {
+                self.report(
+                    item,
+                    Field::Name,
+                    format!(
+                        "{} '{}' is defined more than once!",
+                        section.label(),
+                        item.name
+                    ),
+                );
+            }
+        }
+        items
+    }
+}
+
+/// Whether a program can be run, either as a path or by searching the `PATH`
+fn is_installed(program: &str) -> bool {
+    if program.contains('/') {
+        return Path::new(program).is_file();
+    }
+    std::env::var_os("PATH")
+        .is_some_and(|paths| std::env::split_paths(&paths).any(|dir| dir.join(program).is_file()))
+}
+
+/// Describe the problem with a shell, if it can't be used to run commands
+fn shell_problem(shell: &Shell) -> Option<String> {
+    let Some(argv) = shell.argv() else {
+        return Some(format!("has a 'shell' with an unclosed quote: '{}'", shell));
+    };
+    match argv.first() {
+        None if matches!(shell, Shell::Line(_)) => {
+            Some("has an empty 'shell', use '[]' to run the command without one".to_owned())
+        }
+        Some(program) if !is_installed(program) => {
+            Some(format!("has a 'shell' that isn't installed: '{}'", program))
+        }
+        _ => None,
+    }
+}
+
+/// The Roxfile that an item was defined in, defaulting to the root Roxfile
+fn defined_in<'a>(source: &'a Option<String>, root: &'a str) -> &'a str {
+    source.as_deref().unwrap_or(root)
+}
+
+/// Check a loaded Roxfile for every problem that would prevent it from running.
+///
+/// Problems are pointed at the Roxfile they're defined in,
+/// including any that were merged in via `include`.
+pub fn validate_roxfile(roxfile: &RoxFile, file_path: &str) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        sources: SourceMap::default(),
+        diagnostics: Vec::new(),
+    };
+
+    // Templates
+    let templates = roxfile.templates.as_deref().unwrap_or_default();
+    let template_items = validator.check_duplicates(
+        Section::Templates,
+        templates.iter().map(|template| {
+            (
+                template.name.as_str(),
+                defined_in(&template.file_path, file_path),
+            )
+        }),
+    );
+    for (template, item) in templates.iter().zip(template_items.iter()) {
+        if let Err(e) = template.validate() {
+            let message = format!("Template '{}': {}", template.name, e);
+            validator.report(item, Field::Name, message);
+        }
+        for param in template.params.iter().flatten() {
+            if RESERVED_PARAM_NAMES.contains(&param.name.as_str()) {
+                validator.report(
+                    item,
+                    Field::Value("params", &param.name),
+                    format!(
+                        "Template '{}' has a param named '{}', which is reserved for Rox's own args!",
+                        template.name, param.name
+                    ),
+                );
+            }
+        }
+    }
+
+    let root_shell_problem = roxfile.shell.as_ref().and_then(shell_problem);
+    if let Some(problem) = root_shell_problem {
+        validator.report_top_level(file_path, "shell", format!("The Roxfile {}!", problem));
+    }
+
+    // Tasks
+    let task_items = validator.check_duplicates(
+        Section::Tasks,
+        roxfile
+            .tasks
+            .iter()
+            .map(|task| (task.name.as_str(), defined_in(&task.file_path, file_path))),
+    );
+    let mut task_names: HashSet<String> = HashSet::new();
+    for task in roxfile.tasks.iter() {
+        task_names.insert(task.name.to_owned());
+        task_names.extend(
+            expand_matrix(task)
+                .into_iter()
+                .map(|sub_task| sub_task.name),
+        );
+    }
+    let mut has_unknown_dependency = false;
+    for (task, item) in roxfile.tasks.iter().zip(task_items.iter()) {
+        if let Err(e) = task.validate() {
+            validator.report(item, Field::Name, format!("Task '{}': {}", task.name, e));
+        } else if let Some(uses) = &task.uses {
+            check_template_use(&mut validator, task, item, uses, templates);
+        }
+
+        for dependency in task.depends_on.iter().flatten() {
+            if !task_names.contains(dependency) {
+                has_unknown_dependency = true;
+                validator.report(
+                    item,
+                    Field::Value("depends_on", dependency),
+                    format!(
+                        "Task '{}' depends on non-existent Task '{}'!",
+                        task.name, dependency
+                    ),
+                );
+            }
+        }
+
+        let workdirs: Vec<Task> = match task.matrix {
+            Some(_) => expand_matrix(task),
+            None => vec![task.clone()],
+        };
+        let missing_workdir = workdirs
+            .iter()
+            .filter_map(|task| task.workdir.as_ref())
+            .find(|workdir| !Path::new(workdir).is_dir());
+        if let Some(workdir) = missing_workdir {
+            validator.report(
+                item,
+                Field::Key("workdir"),
+                format!(
+                    "Task '{}' has a 'workdir' that doesn't exist: '{}'!",
+                    task.name, workdir
+                ),
+            );
+        }
+
+        if let Some(problem) = task.shell.as_ref().and_then(shell_problem) {
+            let message = format!("Task '{}' {}!", task.name, problem);
+            validator.report(item, Field::Key("shell"), message);
+        }
+        // Without a shell, the command itself has to be split into args
+        let shell = task.shell.as_ref().or(roxfile.shell.as_ref());
+        let direct = shell
+            .and_then(Shell::argv)
+            .is_some_and(|argv| argv.is_empty());
+        if let Some(command) = task.command.as_ref().filter(|_| direct) {
+            if split_words(command).is_none() {
+                validator.report(
+                    item,
+                    Field::Key("command"),
+                    format!(
+                        "Task '{}' runs its command without a shell, but it has an unclosed quote!",
+                        task.name
+                    ),
+                );
+            }
+        }
+        if let Some(script) = task.script.as_ref().filter(|_| direct) {
+            if !script.starts_with("#!") {
+                validator.report(
+                    item,
+                    Field::Key("script"),
+                    format!(
+                        "Task '{}' runs its script without a shell, but it doesn't start with '#!'!",
+                        task.name
+                    ),
+                );
+            }
+        }
+    }
+    // Cycles can only be searched for once every dependency exists,
+    // which includes the sub-Tasks of matrix Tasks
+    let expanded_tasks = expand_matrix_tasks(roxfile.tasks.clone());
+    let cycle = match has_unknown_dependency {
+        true => None,
+        false => find_dependency_cycle(&expanded_tasks),
+    };
+    if let Some(cycle) = cycle {
+        let message = format!("Task dependency cycle detected: {}", cycle.join(" -> "));
+        // Point at the first dependency in the cycle, which may be declared by a matrix Task
+        let defined_at = roxfile.tasks.iter().position(|task| {
+            cycle[0] == task.name || cycle[0].starts_with(&format!("{}[", task.name))
+        });
+        match defined_at {
+            Some(index) => validator.report(
+                &task_items[index],
+                Field::Value("depends_on", &cycle[1]),
+                message,
+            ),
+            None => validator.diagnostics.push(Diagnostic {
+                message,
+                file_path: file_path.to_owned(),
+                line: None,
+                column: None,
+            }),
+        }
+    }
+
+    // Pipelines
+    let pipelines = roxfile.pipelines.as_deref().unwrap_or_default();
+    let pipeline_items = validator.check_duplicates(
+        Section::Pipelines,
+        pipelines.iter().map(|pipeline| {
+            (
+                pipeline.name.as_str(),
+                defined_in(&pipeline.file_path, file_path),
+            )
+        }),
+    );
+    for (pipeline, item) in pipelines.iter().zip(pipeline_items.iter()) {
+        for task_name in pipeline.stages.iter().flatten() {
+            if !task_names.contains(task_name) {
+                validator.report(
+                    item,
+                    Field::Value("stages", task_name),
+                    format!(
+                        "Pipeline '{}' includes non-existent Task '{}'!",
+                        pipeline.name, task_name
+                    ),
+                );
+            }
+        }
+    }
+
+    // Docs
+    let docs = roxfile.docs.as_deref().unwrap_or_default();
+    let docs_items = validator.check_duplicates(
+        Section::Docs,
+        docs.iter()
+            .map(|doc| (doc.name.as_str(), defined_in(&doc.file_path, file_path))),
+    );
+    for (doc, item) in docs.iter().zip(docs_items.iter()) {
+        if !matches!(doc.kind, DocsKind::URL) && !Path::new(&doc.path).exists() {
+            validator.report(
+                item,
+                Field::Key("path"),
+                format!(
+                    "Docs '{}' has a 'path' that doesn't exist: '{}'!",
+                    doc.name, doc.path
+                ),
+            );
+        }
+    }
+
+    // Report problems in the order they appear, keeping each file together
+    let mut diagnostics = validator.diagnostics;
+    let mut files: Vec<String> = Vec::new();
+    for diagnostic in diagnostics.iter() {
+        if !files.contains(&diagnostic.file_path) {
+            files.push(diagnostic.file_path.to_owned());
+        }
+    }
+    diagnostics.sort_by_key(|diagnostic| {
+        let file = files.iter().position(|file| *file == diagnostic.file_path);
+        (file, diagnostic.line, diagnostic.column)
+    });
+    diagnostics
+}
+
+/// Check that a Task's Template exists and that its values bind to it
+fn check_template_use(
+    validator: &mut Validator,
+    task: &Task,
+    item: &Item,
+    uses: &str,
+    templates: &[crate::models::Template],
+) {
+    let Some(template) = templates.iter().find(|template| template.name == uses) else {
+        validator.report(
+            item,
+            Field::Key("uses"),
+            format!(
+                "Task '{}' uses non-existent Template '{}'!",
+                task.name, uses
+            ),
+        );
+        return;
+    };
+
+    if let (Some(values), None) = (&task.values, &template.params) {
+        if values.len() != template.symbols.len() {
+            validator.report(
+                item,
+                Field::Key("values"),
+                format!(
+                    "Task '{}' passes {} values to Template '{}', which expects {}!",
+                    task.name,
+                    values.len(),
+                    template.name,
+                    template.symbols.len()
+                ),
+            );
+        }
+        return;
+    }
+
+    if let Err(e) = template.validate_bindings(task) {
+        let field = match task.with {
+            Some(_) => Field::Key("with"),
+            None => Field::Key("values"),
+        };
+        validator.report(item, field, format!("Task '{}': {}", task.name, e));
+    }
+}
+
+/// Load and validate a Roxfile, reporting every problem found
+pub fn check_roxfile(file_path: &str) -> Vec<Diagnostic> {
+    match load_roxfile(file_path) {
+        Ok(roxfile) => validate_roxfile(&roxfile, file_path),
+        Err(e) => vec![to_diagnostic(e, file_path)],
+    }
+}
+
+/// Convert an error from loading a Roxfile, keeping its location if it has one
+pub fn to_diagnostic(error: anyhow::Error, file_path: &str) -> Diagnostic {
+    match error.downcast::<Diagnostic>() {
+        Ok(diagnostic) => diagnostic,
+        Err(e) => Diagnostic {
+            message: e.to_string(),
+            file_path: file_path.to_owned(),
+            line: None,
+            column: None,
+        },
+    }
+}
+
+/// Display the problems within a Roxfile, failing if there are any
+pub fn display_validation(file_path: &str, output: OutputFormat) -> Result<(), RoxError> {
+    let diagnostics = check_roxfile(file_path);
+
+    match output {
+        OutputFormat::Json => print_json(&diagnostics),
+        OutputFormat::Ndjson => diagnostics.iter().for_each(print_json_line),
+        OutputFormat::Text if diagnostics.is_empty() => {
+            println!("> Roxfile '{}' is valid!", file_path)
+        }
+        // The problems themselves are reported by the error
+        OutputFormat::Text => {}
+    }
+
+    match diagnostics.is_empty() {
+        true => Ok(()),
+        false => Err(RoxError::Validation(diagnostics)),
+    }
+}
+
+#[test]
+fn locate_points_at_fields() {
+    let mut sources = SourceMap::default();
+    sources.files.insert(
+        "roxfile.yml".to_owned(),
+        [
+            "tasks:",
+            "  - name: build",
+            "    command: cargo build",
+            "",
+            "  - name: \"test\"",
+            "    depends_on: [\"build\", \"lint\"]",
+            "    command: cargo test",
+            "pipelines:",
+            "- name: test",
+            "  stages: [[\"test\"]]",
+        ]
+        .map(str::to_owned)
+        .to_vec(),
+    );
+    let item = |section, name| Item {
+        section,
+        name,
+        file_path: "roxfile.yml",
+        occurrence: 0,
+    };
+
+    assert_eq!(
+        sources.locate(&item(Section::Tasks, "test"), &Field::Name),
+        Some((5, 5))
+    );
+    assert_eq!(
+        sources.locate(
+            &item(Section::Tasks, "test"),
+            &Field::Value("depends_on", "lint")
+        ),
+        Some((6, 28))
+    );
+    assert_eq!(
+        sources.locate(&item(Section::Tasks, "build"), &Field::Key("workdir")),
+        Some((2, 5))
+    );
+    assert_eq!(
+        sources.locate(&item(Section::Pipelines, "test"), &Field::Key("stages")),
+        Some((10, 3))
+    );
+}
@@ -0,0 +1,142 @@
+//! Rerun Tasks & Pipelines when the files they depend on change
+use crate::models::Task;
+use crate::modules::execution::dependencies::resolve_execution_layers;
+use crate::modules::execution::processes;
+use crate::modules::logs::LOG_DIR;
+use git2::Repository;
+use glob::Pattern;
+use notify::{Event, EventKind, RecursiveMode, Watcher};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+/// How long to wait for changes to settle before rerunning
+const DEBOUNCE: Duration = Duration::from_millis(500);
+
+/// The files that a single Task depends on
+struct WatchTarget {
+    root: PathBuf,
+    patterns: Option<Vec<Pattern>>,
+}
+
+impl WatchTarget {
+    fn from_task(task: &Task) -> Self {
+        let workdir = task.workdir.clone().unwrap_or(".".to_string());
+        let root = std::fs::canonicalize(&workdir).unwrap_or(PathBuf::from(workdir));
+        let patterns = task.inputs.as_ref().map(|inputs| {
+            inputs
+                .iter()
+                .filter_map(|input| Pattern::new(&root.join(input).to_string_lossy()).ok())
+                .collect()
+        });
+        WatchTarget { root, patterns }
+    }
+
+    /// Declared inputs are always watched, otherwise any file within
+    /// the working directory that isn't ignored by git is watched.
+    fn matches(&self, path: &Path, repo: &Option<Repository>) -> bool {
+        if !path.starts_with(&self.root) || path.components().any(|c| c.as_os_str() == LOG_DIR) {
+            return false;
+        }
+        match &self.patterns {
+            Some(patterns) => patterns.iter().any(|pattern| pattern.matches_path(path)),
+            None => !is_git_ignored(path, repo),
+        }
+    }
+}
+
+fn is_git_ignored(path: &Path, repo: &Option<Repository>) -> bool {
+    let Some(repo) = repo else {
+        return false;
+    };
+    let Some(workdir) = repo
+        .workdir()
+        .and_then(|dir| std::fs::canonicalize(dir).ok())
+    else {
+        return false;
+    };
+    match path.strip_prefix(workdir) {
+        Ok(relative_path) => repo.is_path_ignored(relative_path).unwrap_or(false),
+        Err(_) => false,
+    }
+}
+
+/// Block until a relevant file changes, returning its path
+fn wait_for_change(
+    receiver: &Receiver<notify::Result<Event>>,
+    targets: &[WatchTarget],
+    repo: &Option<Repository>,
+) -> Option<PathBuf> {
+    loop {
+        let event = match receiver.recv() {
+            Ok(Ok(event)) => event,
+            Ok(Err(_)) => continue,
+            Err(_) => return None,
+        };
+        if !matches!(
+            event.kind,
+            EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+        ) {
+            continue;
+        }
+        let changed = event
+            .paths
+            .into_iter()
+            .find(|path| targets.iter().any(|target| target.matches(path, repo)));
+        if changed.is_some() {
+            return changed;
+        }
+    }
+}
+
+/// Run the targets, rerunning them whenever the files that they
+/// or their prerequisites depend on are changed.
+///
+/// Any run that is still in progress when a change
+/// is detected is cancelled before rerunning.
+pub fn watch(
+    targets: &[String],
+    task_map: &HashMap<String, Task>,
+    run: impl Fn() + Sync,
+) -> notify::Result<()> {
+    let watch_targets: Vec<WatchTarget> =
+        resolve_execution_layers(targets, task_map, &HashSet::new())
+            .iter()
+            .flatten()
+            .map(|name| WatchTarget::from_task(&task_map[name]))
+            .collect();
+    let repo = Repository::discover(".").ok();
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)?;
+    let mut roots: Vec<&PathBuf> = watch_targets.iter().map(|target| &target.root).collect();
+    roots.sort();
+    roots.dedup();
+    for root in roots.iter() {
+        watcher.watch(root, RecursiveMode::Recursive)?;
+    }
+    println!("> Watching for changes in: {:?}", roots);
+    processes::set_watching();
+
+    std::thread::scope(|scope| loop {
+        processes::reset();
+        let execution = scope.spawn(&run);
+
+        let Some(changed) = wait_for_change(&receiver, &watch_targets, &repo) else {
+            return Ok(());
+        };
+        // Wait for things to settle before rerunning
+        loop {
+            match receiver.recv_timeout(DEBOUNCE) {
+                Ok(_) => continue,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return Ok(()),
+            }
+        }
+
+        println!("> Change detected in '{}', rerunning...", changed.display());
+        processes::cancel(libc::SIGTERM);
+        execution.join().unwrap();
+    })
+}
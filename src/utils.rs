@@ -1,27 +1,84 @@
 //! Utility Functions
-use std::fmt::Display;
 
-use colored::Colorize;
+/// A reusable println! to serve as a visual break
+pub fn print_horizontal_rule() {
+    println!("-------------------------------------------");
+}
+
+/// Replace any characters that aren't safe to use in a filename
+pub fn sanitize_filename(name: &str) -> String {
+    name.chars()
+        .map(|c| match c.is_alphanumeric() || c == '-' || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect()
+}
 
-pub enum ColorEnum {
-    Red,
+/// Quote a string so that the shell treats it as a single word
+pub fn shell_quote(word: &str) -> String {
+    let is_safe = !word.is_empty()
+        && word
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_=+./,:@%".contains(c));
+    if is_safe {
+        return word.to_owned();
+    }
+    format!("'{}'", word.replace('\'', "'\\''"))
 }
 
-/// Print a string as a certain color
-pub fn color_print<T>(outputs: Vec<T>, color: ColorEnum)
-where
-    T: Display + Into<String>,
-{
-    let concat_output = outputs
-        .iter()
-        .fold("".to_string(), |x, y| format!("{}{}", x, y));
+/// Split a command line into words like a POSIX shell would, without any expansion.
+///
+/// Returns `None` if a quote is left unclosed.
+pub fn split_words(line: &str) -> Option<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = line.chars();
 
-    match color {
-        ColorEnum::Red => println!("{}", concat_output.red()),
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\\' => {
+                let word = word.get_or_insert_with(String::new);
+                word.extend(chars.next());
+            }
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            c => word.extend(['\\', c]),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
     }
+    words.extend(word);
+    Some(words)
 }
 
-/// A reusable println! to serve as a visual break
-pub fn print_horizontal_rule() {
-    println!("-------------------------------------------");
+#[test]
+fn split_words_undoes_quoting() {
+    let words = ["plain", "it's", "a \"b\" $c", ""];
+    let line = words.map(shell_quote).join(" ");
+    assert_eq!(split_words(&line).unwrap(), words);
+    assert_eq!(
+        split_words(r#"bash  -c "echo \"hi\"" a\ b"#).unwrap(),
+        vec!["bash", "-c", "echo \"hi\"", "a b"]
+    );
+    assert!(split_words("echo 'unclosed").is_none());
 }
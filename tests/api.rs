@@ -0,0 +1,157 @@
+use rox::errors::{ExecutionError, RoxError};
+use rox::models::PassFail;
+use rox::reporter::{EventReporter, ExecutionEvent};
+use rox::Rox;
+use std::sync::{Arc, Mutex};
+
+const ROXFILE: &str = r#"
+env:
+  ROX_API_VAR: roxfile
+
+tasks:
+  - name: passing
+    command: echo passing
+
+  - name: failing
+    command: "false"
+
+  - name: env_task
+    command: test "$ROX_API_VAR" = "$ROX_API_EXPECTED"
+    env:
+      ROX_API_EXPECTED: roxfile
+
+  - name: dependent
+    command: echo dependent
+    depends_on: [passing]
+
+pipelines:
+  - name: ci
+    stages: [["failing"], ["passing"]]
+"#;
+
+#[test]
+fn lists_tasks_and_pipelines() {
+    let rox = Rox::from_path("tests/files/test_roxfile.yml").unwrap();
+    assert!(rox.tasks().iter().any(|task| task.name == "passing"));
+    assert!(rox.tasks().iter().any(|task| task.name == "inc:included"));
+    assert!(rox
+        .pipelines()
+        .iter()
+        .any(|pipeline| pipeline.name == "passing_single"));
+}
+
+#[test]
+fn run_task_returns_results() {
+    let results = Rox::from_yaml(ROXFILE)
+        .unwrap()
+        .run_task("dependent")
+        .unwrap();
+    let names: Vec<&str> = results.results.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(names, vec!["passing", "dependent"]);
+    assert!(results.check_results().is_ok());
+}
+
+#[test]
+fn env_overrides_take_precedence() {
+    let rox = Rox::from_yaml(ROXFILE).unwrap();
+    assert!(rox.run_task("env_task").unwrap().check_results().is_ok());
+
+    let results = rox
+        .env("ROX_API_VAR", "override")
+        .run_task("env_task")
+        .unwrap();
+    assert!(matches!(
+        results.check_results(),
+        Err(RoxError::Execution(ExecutionError::Failed(_)))
+    ));
+}
+
+#[test]
+fn run_pipeline_respects_keep_going() {
+    let rox = Rox::from_yaml(ROXFILE).unwrap();
+    let results = rox.run_pipeline("ci").unwrap();
+    assert_eq!(results.results[1].result, PassFail::NotRun);
+
+    let results = rox.keep_going(true).run_pipeline("ci").unwrap();
+    assert_eq!(results.results[1].result, PassFail::Pass);
+}
+
+#[test]
+fn unknown_names_are_errors() {
+    let rox = Rox::from_yaml(ROXFILE).unwrap();
+    assert!(matches!(
+        rox.run_task("missing"),
+        Err(RoxError::Validation(_))
+    ));
+    assert!(matches!(
+        rox.run_pipeline("missing"),
+        Err(RoxError::Validation(_))
+    ));
+}
+
+#[test]
+fn invalid_yaml_is_a_parse_error() {
+    let Err(RoxError::Parse(diagnostic)) = Rox::from_yaml("tasks: [") else {
+        panic!("Expected a parse error");
+    };
+    assert_eq!(diagnostic.file_path, "<string>");
+    assert!(diagnostic.line.is_some());
+}
+
+#[test]
+fn reporters_receive_events() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let recorded = Arc::clone(&events);
+    let results = Rox::from_yaml(ROXFILE)
+        .unwrap()
+        .reporter(EventReporter::new(move |event| {
+            recorded.lock().unwrap().push(event)
+        }))
+        .run_task("passing")
+        .unwrap();
+
+    let events = events.lock().unwrap();
+    assert!(matches!(
+        events.first(),
+        Some(ExecutionEvent::JobStarted { job_name }) if job_name == "passing"
+    ));
+    assert!(events.iter().any(|event| matches!(
+        event,
+        ExecutionEvent::Output { line, .. } if line == "passing"
+    )));
+    assert!(matches!(
+        events.last(),
+        Some(ExecutionEvent::JobFinished { job }) if job.results.len() == results.results.len()
+    ));
+}
+
+#[test]
+fn reserved_param_names_are_rejected() {
+    for name in ["output", "junit"] {
+        let roxfile = format!(
+            r#"
+templates:
+  - name: report
+    command: "cargo run -- --format {{{name}}}"
+    params:
+      - name: {name}
+
+tasks:
+  - name: build_report
+    uses: report
+    with:
+      {name}: json
+"#
+        );
+        let Err(RoxError::Validation(diagnostics)) = Rox::from_yaml(&roxfile) else {
+            panic!("Expected a validation error");
+        };
+        assert_eq!(
+            diagnostics[0].message,
+            format!(
+                "Template 'report' has a param named '{}', which is reserved for Rox's own args!",
+                name
+            )
+        );
+    }
+}
@@ -91,3 +91,845 @@ fn hidden_task() {
         .success()
         .stdout(predicate::str::contains(expected).count(0));
 }
+
+#[test]
+fn task_runs_dependencies_first() {
+    test_command()
+        .arg("task")
+        .arg("dependent_nested")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("> Running command: 'echo test'").count(1))
+        .stdout(predicate::str::contains("> Running command: 'echo dependent'").count(1));
+}
+
+#[test]
+fn pipeline_runs_dependencies_once() {
+    test_command()
+        .arg("pl")
+        .arg("dependencies_multi")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("> Running command: 'echo test'").count(1));
+}
+
+#[test]
+fn dependency_cycle_fails() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.arg("-f")
+        .arg("tests/files/cycle_roxfile.yml")
+        .arg("task")
+        .arg("first")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "tests/files/cycle_roxfile.yml:4:19: Task dependency cycle detected: first -> second -> first",
+        ));
+}
+
+#[test]
+fn task_env_precedence() {
+    test_command()
+        .arg("task")
+        .arg("env_task")
+        .assert()
+        .success();
+}
+
+#[test]
+fn pipeline_env_precedence() {
+    test_command()
+        .arg("pl")
+        .arg("env_pipeline")
+        .assert()
+        .success();
+}
+
+#[test]
+fn cli_env_overrides() {
+    test_command()
+        .arg("task")
+        .arg("env_task")
+        .arg("--env")
+        .arg("ROX_PRECEDENCE=cli")
+        .arg("-e")
+        .arg("ROX_EXPECTED=cli")
+        .assert()
+        .success();
+}
+
+#[test]
+fn named_template_uses_defaults() {
+    test_command()
+        .arg("task")
+        .arg("named_template")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello, world"));
+}
+
+#[test]
+fn named_template_params_override() {
+    test_command()
+        .arg("task")
+        .arg("named_template")
+        .arg("--target")
+        .arg("rox")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello, rox"));
+
+    test_command()
+        .arg("task")
+        .arg("named_template")
+        .arg("--set")
+        .arg("greeting=goodbye")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("goodbye, world"));
+}
+
+#[test]
+fn unknown_template_param_fails() {
+    test_command()
+        .arg("task")
+        .arg("named_template")
+        .arg("--set")
+        .arg("unknown=value")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Template 'greet' has no param named 'unknown'!",
+        ));
+}
+
+#[test]
+fn task_args_are_passed_through() {
+    test_command()
+        .arg("task")
+        .arg("passing")
+        .arg("--")
+        .arg("--nocapture")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "> Running command: 'echo test --nocapture'",
+        ));
+
+    test_command()
+        .arg("task")
+        .arg("args_placeholder")
+        .arg("--")
+        .arg("a b")
+        .assert()
+        .success();
+}
+
+#[test]
+fn task_times_out() {
+    let start = std::time::Instant::now();
+    test_command()
+        .arg("task")
+        .arg("timed_out")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains(
+            "> Task 'timed_out' timed out after 1s",
+        ))
+        .stdout(predicate::str::contains("TimedOut"));
+    assert!(start.elapsed().as_secs() < 10);
+}
+
+#[test]
+fn failed_task_is_retried() {
+    test_command()
+        .arg("task")
+        .arg("flaky")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "> Retrying 'flaky' in 0s (attempt 2 of 3)",
+        ));
+}
+
+#[test]
+fn failing_stage_stops_pipeline() {
+    test_command()
+        .arg("pl")
+        .arg("failing_multi")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("NotRun"))
+        .stdout(predicate::str::contains("> Running command: 'echo test'").not());
+}
+
+#[test]
+fn failing_stage_continues_pipeline() {
+    test_command()
+        .arg("pl")
+        .arg("failing_continue")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("> Running command: 'echo test'"));
+
+    test_command()
+        .arg("pl")
+        .arg("--keep-going")
+        .arg("failing_multi")
+        .assert()
+        .code(2)
+        .stdout(predicate::str::contains("> Running command: 'echo test'"));
+}
+
+#[test]
+fn continued_pipeline_skips_tasks_with_failed_prerequisites() {
+    let output = test_command()
+        .args(["pl", "failing_prerequisite_continue", "--output", "json"])
+        .output()
+        .unwrap();
+    assert_eq!(output.status.code(), Some(2));
+
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let result_of = |name: &str| {
+        results["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|result| result["name"] == name)
+            .map(|result| result["result"].clone())
+    };
+    assert_eq!(result_of("failing_dependent").unwrap(), "NotRun");
+    assert_eq!(result_of("passing").unwrap(), "Pass");
+}
+
+#[test]
+fn allowed_failure_passes_pipeline() {
+    test_command()
+        .arg("pl")
+        .arg("allowed_failure_multi")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Fail (allowed)"))
+        .stdout(predicate::str::contains("> Running command: 'echo test'"));
+}
+
+/// Start a task, then send rox a signal once it's running
+fn signal_task(task: &str, signal: &str) -> (std::process::ExitStatus, String) {
+    use std::io::Read;
+    use std::time::Duration;
+
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("rox"))
+        .args(["-f", "tests/files/test_roxfile.yml", "task", task])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    std::thread::sleep(Duration::from_millis(500));
+    std::process::Command::new("kill")
+        .args([signal, &child.id().to_string()])
+        .status()
+        .unwrap();
+    let status = child.wait().unwrap();
+
+    let mut stdout = String::new();
+    child.stdout.unwrap().read_to_string(&mut stdout).unwrap();
+    (status, stdout)
+}
+
+#[test]
+fn interrupt_cancels_and_logs() {
+    let start = std::time::Instant::now();
+    let (status, stdout) = signal_task("timed_out", "-INT");
+    assert_eq!(status.code(), Some(130));
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    assert!(stdout.contains("Cancelled"));
+    assert!(stdout.contains("> Log file written to:"));
+}
+
+#[test]
+fn interrupt_is_forwarded_to_tasks() {
+    let (status, stdout) = signal_task("trap_interrupt", "-INT");
+    assert_eq!(status.code(), Some(130));
+    assert!(stdout.contains("interrupted"));
+}
+
+#[test]
+fn interrupt_stops_retry_backoff() {
+    let start = std::time::Instant::now();
+    let (status, stdout) = signal_task("slow_retry", "-INT");
+    assert_eq!(status.code(), Some(130));
+    assert!(start.elapsed() < std::time::Duration::from_secs(5));
+    assert!(stdout.contains("Cancelled"));
+}
+
+#[test]
+fn task_reads_from_the_terminal() {
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::os::fd::FromRawFd;
+    use std::os::unix::process::CommandExt;
+    use std::time::{Duration, Instant};
+
+    // Run rox as the foreground process group of a new terminal
+    let (mut master, slave) = unsafe {
+        let (mut master, mut slave) = (0, 0);
+        let opened = libc::openpty(
+            &mut master,
+            &mut slave,
+            std::ptr::null_mut(),
+            std::ptr::null(),
+            std::ptr::null(),
+        );
+        assert_eq!(opened, 0);
+        (File::from_raw_fd(master), File::from_raw_fd(slave))
+    };
+    let mut command = std::process::Command::new(assert_cmd::cargo::cargo_bin("rox"));
+    command
+        .args(["-f", "tests/files/test_roxfile.yml", "task", "read_stdin"])
+        .stdin(slave.try_clone().unwrap())
+        .stdout(slave.try_clone().unwrap())
+        .stderr(slave);
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            libc::ioctl(0, libc::TIOCSCTTY, 0);
+            Ok(())
+        });
+    }
+    let mut child = command.spawn().unwrap();
+    drop(command);
+    master.write_all(b"hello\n").unwrap();
+
+    let reader = std::thread::spawn(move || {
+        let mut output = Vec::new();
+        // Reading fails once every copy of the other end is closed
+        let _ = master.read_to_end(&mut output);
+        String::from_utf8_lossy(&output).into_owned()
+    });
+    let start = Instant::now();
+    while child.try_wait().unwrap().is_none() {
+        if start.elapsed() > Duration::from_secs(10) {
+            child.kill().unwrap();
+            panic!("The task hung while reading from the terminal");
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+
+    assert!(child.wait().unwrap().success());
+    assert!(reader.join().unwrap().contains("got=hello"));
+}
+
+#[test]
+fn task_json_output() {
+    let output = test_command()
+        .args(["task", "dependent", "--output", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results["job_name"], "dependent");
+    assert_eq!(results["results"][0]["name"], "passing");
+    assert_eq!(results["results"][1]["result"], "Pass");
+}
+
+#[test]
+fn pipeline_ndjson_output() {
+    let output = test_command()
+        .args(["pl", "passing_single", "--output", "ndjson"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let events: Vec<serde_json::Value> = String::from_utf8(output.stdout)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(events[0]["event"], "job_started");
+    assert_eq!(events[1]["event"], "stage_started");
+    assert_eq!(events[2]["event"], "started");
+    assert_eq!(events[3]["line"], "test");
+    assert_eq!(events[3]["stream"], "stdout");
+    let last = events.last().unwrap();
+    assert_eq!(last["event"], "job_finished");
+    assert_eq!(last["job"]["job_name"], "passing_single");
+}
+
+#[test]
+fn list_json_output() {
+    let output = test_command()
+        .args(["list", "--output", "json"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let listing: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let task_names: Vec<&str> = listing["tasks"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|task| task["name"].as_str().unwrap())
+        .collect();
+    assert!(task_names.contains(&"passing"));
+    assert!(task_names.contains(&"hidden"));
+    assert_eq!(listing["templates"][0]["name"], "greet");
+}
+
+#[test]
+fn list_hides_hidden_tasks() {
+    test_command()
+        .arg("list")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("passing"))
+        .stdout(predicate::str::contains("hidden").not());
+}
+
+#[test]
+fn pipeline_writes_junit_report() {
+    let report = ".rox/test-reports/failing_multi.xml";
+    test_command()
+        .args(["pl", "failing_multi", "--junit", report])
+        .assert()
+        .code(2);
+
+    let xml = std::fs::read_to_string(report).unwrap();
+    assert!(xml.contains("<testsuite name=\"failing_multi - Stage 1\""));
+    assert!(xml.contains("<failure message=\"Exited with code 1\" type=\"Fail\">false</failure>"));
+    assert!(xml.contains("<skipped message=\"Not run\"/>"));
+}
+
+#[test]
+fn logs_export_junit() {
+    test_command().arg("task").arg("passing").assert().success();
+    let output = test_command()
+        .args(["logs", "20", "--export", "junit"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // The report has to be valid on its own so that it can be redirected to a file
+    let report = String::from_utf8(output.stdout).unwrap();
+    let document = roxmltree::Document::parse(&report).unwrap();
+    assert_eq!(document.root_element().tag_name().name(), "testsuites");
+    assert!(document
+        .descendants()
+        .any(|node| node.has_tag_name("testcase") && node.attribute("name") == Some("passing")));
+}
+
+#[test]
+fn matrix_task_runs_every_combination() {
+    test_command()
+        .arg("task")
+        .arg("matrix")
+        .arg("--")
+        .arg("done")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "> Running command: 'echo small-red done'",
+        ))
+        .stdout(predicate::str::contains(
+            "> Running command: 'echo large-red done'",
+        ));
+}
+
+#[test]
+fn task_depends_on_matrix_sub_task() {
+    test_command()
+        .args(["task", "matrix_dependent"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "> Running command: 'echo small-red'",
+        ))
+        .stdout(predicate::str::contains(
+            "> Running command: 'echo matrix_dependent'",
+        ));
+}
+
+#[test]
+fn matrix_sub_task_runs_individually() {
+    test_command()
+        .arg("task")
+        .arg("matrix[color=red,size=large]")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "> Running command: 'echo large-red'",
+        ))
+        .stdout(predicate::str::contains("small-red").not());
+}
+
+#[test]
+fn logs_show_task_output() {
+    test_command().arg("task").arg("passing").assert().success();
+    test_command()
+        .arg("logs")
+        .arg("100")
+        .arg("--task")
+        .arg("passing")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("| passing | Pass\ntest\n"));
+}
+
+#[test]
+fn unchanged_task_is_cached() {
+    test_command().arg("task").arg("cached").assert().success();
+    test_command()
+        .arg("task")
+        .arg("cached")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "> Skipping 'cached', its inputs are unchanged",
+        ))
+        .stdout(predicate::str::contains("Cached"));
+}
+
+#[test]
+fn cached_task_reruns_when_env_changes() {
+    test_command()
+        .args(["-e", "V=one", "task", "cached_env"])
+        .assert()
+        .success();
+    test_command()
+        .args(["-e", "V=two", "task", "cached_env"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("V=two"))
+        .stdout(predicate::str::contains("Skipping").not());
+}
+
+#[test]
+fn watch_reruns_on_change() {
+    use std::io::Read;
+    use std::time::Duration;
+
+    let trigger = "tests/files/watch_trigger.txt";
+    let mut child = std::process::Command::new(assert_cmd::cargo::cargo_bin("rox"))
+        .args([
+            "-f",
+            "tests/files/test_roxfile.yml",
+            "task",
+            "watched",
+            "--watch",
+        ])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    std::thread::sleep(Duration::from_secs(2));
+    std::fs::write(trigger, "changed").unwrap();
+    std::thread::sleep(Duration::from_secs(2));
+    child.kill().unwrap();
+    child.wait().unwrap();
+    let _ = std::fs::remove_file(trigger);
+
+    let mut stdout = String::new();
+    child.stdout.unwrap().read_to_string(&mut stdout).unwrap();
+    assert!(stdout.contains("> Change detected in"));
+    assert_eq!(
+        stdout.matches("> Running command: 'echo watched'").count(),
+        2
+    );
+}
+
+#[test]
+fn included_pipeline_succeeds() {
+    test_command()
+        .arg("pl")
+        .arg("inc:included_pipeline")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "> Running command: 'echo included'",
+        ))
+        .stdout(predicate::str::contains(
+            "> Running command: 'echo templated'",
+        ));
+}
+
+#[test]
+fn include_name_collision_fails() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.arg("-f")
+        .arg("tests/files/collision_roxfile.yml")
+        .arg("task")
+        .arg("included")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Task 'included' is defined in multiple Roxfiles!",
+        ));
+}
+
+#[test]
+fn diamond_include_is_merged_once() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.arg("-f")
+        .arg("tests/files/diamond_roxfile.yml")
+        .arg("task")
+        .arg("b")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("> Running command: 'echo shared'"));
+}
+
+#[test]
+fn validate_succeeds() {
+    test_command()
+        .arg("validate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("is valid!"));
+}
+
+#[test]
+fn validate_reports_every_problem() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.arg("-f")
+        .arg("tests/files/invalid_roxfile.yml")
+        .arg("validate")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "tests/files/invalid_roxfile.yml:14:5: Task 'unknown_template' uses non-existent Template 'missing_template'!",
+        ))
+        .stderr(predicate::str::contains(
+            "tests/files/invalid_roxfile.yml:19:5: Task 'wrong_values' passes 2 values to Template 'docker_build', which expects 1!",
+        ))
+        .stderr(predicate::str::contains(
+            "tests/files/invalid_roxfile.yml:23:5: Task 'missing_workdir' has a 'workdir' that doesn't exist",
+        ))
+        .stderr(predicate::str::contains(
+            "tests/files/invalid_roxfile.yml:28:5: Task 'duplicate' is defined more than once!",
+        ))
+        .stderr(predicate::str::contains(
+            "tests/files/invalid_roxfile.yml:42:31: Pipeline 'ci' includes non-existent Task 'not_a_task'!",
+        ))
+        .stderr(predicate::str::contains(
+            "tests/files/invalid_roxfile.yml:5:5: Docs 'missing_docs' has a 'path' that doesn't exist",
+        ))
+        .stderr(predicate::str::contains(
+            "tests/files/invalid_roxfile.yml:33:5: Task 'missing_shell' has a 'shell' that isn't installed: 'not-a-real-shell'!",
+        ))
+        .stderr(predicate::str::contains(
+            "tests/files/invalid_roxfile.yml:37:5: Task 'script_without_shebang' runs its script without a shell, but it doesn't start with '#!'!",
+        ))
+        .stderr(predicate::str::contains("Found 8 problem(s)"));
+}
+
+#[test]
+fn validate_json_output() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    let output = cmd
+        .arg("-f")
+        .arg("tests/files/invalid_roxfile.yml")
+        .arg("-o")
+        .arg("json")
+        .arg("validate")
+        .output()
+        .unwrap();
+    let diagnostics: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(diagnostics.as_array().unwrap().len(), 8);
+    assert_eq!(diagnostics[1]["line"], 14);
+    assert_eq!(diagnostics[1]["column"], 5);
+}
+
+#[test]
+fn invalid_roxfile_fails_without_panicking() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.arg("-f")
+        .arg("tests/files/invalid_roxfile.yml")
+        .arg("task")
+        .arg("unknown_template")
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains(
+            "uses non-existent Template 'missing_template'!",
+        ))
+        .stderr(predicate::str::contains("panicked").not());
+}
+
+#[test]
+fn schema_is_printed_without_roxfile() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    let output = cmd
+        .arg("-f")
+        .arg("does_not_exist.yml")
+        .arg("schema")
+        .output()
+        .unwrap();
+    let schema: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(schema["title"], "Roxfile");
+    assert!(schema["definitions"]["Pipeline"].is_object());
+    // Fields that are set automatically aren't part of the Roxfile
+    assert!(schema["definitions"]["Task"]["properties"]["file_path"].is_null());
+}
+
+#[test]
+fn completions_script_calls_back_into_rox() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.arg("completions")
+        .arg("bash")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("rox __complete"));
+}
+
+#[test]
+fn complete_task_names_from_roxfile() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.arg("__complete")
+        .arg("--index")
+        .arg("3")
+        .arg("--")
+        .arg("-f")
+        .arg("tests/files/test_roxfile.yml")
+        .arg("task")
+        .arg("matrix[")
+        .assert()
+        .success()
+        .stdout("matrix[color=red,size=small]\nmatrix[color=red,size=large]\n");
+}
+
+#[test]
+fn bash_completion_keeps_names_with_word_breaks() {
+    let bin_dir = assert_cmd::cargo::cargo_bin("rox")
+        .parent()
+        .unwrap()
+        .to_owned();
+    let path = format!("{}:{}", bin_dir.display(), std::env::var("PATH").unwrap());
+    // Bash only replaces the part of the word after the last ':' or '='
+    let script = r#"
+        source <(rox completions bash)
+        for COMP_LINE in "rox -f tests/files/test_roxfile.yml task inc:inc" \
+            "rox -f tests/files/test_roxfile.yml task matrix[color=red,size=s"; do
+            COMP_POINT=${#COMP_LINE}
+            _rox
+            printf '%s\n' "${COMPREPLY[@]}"
+        done
+    "#;
+    Command::new("bash")
+        .args(["-c", script])
+        .env("PATH", path)
+        .assert()
+        .success()
+        .stdout("included\nincluded_template\nsmall]\n");
+}
+
+#[test]
+fn pick_requires_terminal() {
+    test_command()
+        .arg("pick")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "The picker requires an interactive terminal!",
+        ));
+}
+
+#[test]
+fn workdir_is_relative_to_roxfile() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.current_dir("tests")
+        .arg("-f")
+        .arg("files/test_roxfile.yml")
+        .arg("task")
+        .arg("relative_workdir")
+        .assert()
+        .success();
+}
+
+#[test]
+fn workdir_and_logs_default_to_roxfile_directory() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.current_dir("tests")
+        .args(["-f", "files/test_roxfile.yml", "task", "default_workdir"])
+        .assert()
+        .success();
+    assert!(!std::path::Path::new("tests/.rox").exists());
+
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.current_dir("tests")
+        .args([
+            "-f",
+            "files/test_roxfile.yml",
+            "logs",
+            "100",
+            "--task",
+            "default_workdir",
+        ])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("| default_workdir | Pass"));
+}
+
+#[test]
+fn task_runs_with_its_shell() {
+    test_command().args(["task", "pipefail"]).assert().code(2);
+    test_command()
+        .args(["task", "python_shell"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("from python"));
+    test_command()
+        .args(["task", "direct_exec"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("a b|c|"));
+}
+
+#[test]
+fn script_runs_with_args() {
+    test_command()
+        .args(["task", "script", "--", "one", "two"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("hello from a script"))
+        .stdout(predicate::str::contains("args: one two"));
+}
+
+#[test]
+fn failing_script_reports_its_line() {
+    for (task, line) in [
+        ("failing_script", 2),
+        ("failing_combined_flags_script", 2),
+        ("tolerated_failure_script", 3),
+        ("failing_sh_script", 2),
+        ("failing_python_script", 3),
+    ] {
+        test_command()
+            .args(["task", task])
+            .assert()
+            .code(2)
+            .stdout(predicate::str::contains(format!(
+                "> Task '{}' failed at line {} of its script",
+                task, line
+            )));
+    }
+    let output = test_command()
+        .args(["-o", "json", "task", "failing_script"])
+        .output()
+        .unwrap();
+    let results: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results["results"][0]["failed_line"], 2);
+}
+
+#[test]
+fn roxfile_is_found_in_parent_directory() {
+    let mut cmd = Command::cargo_bin("rox").unwrap();
+    cmd.current_dir("src/modules")
+        .arg("validate")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(
+            "> Roxfile '../../roxfile.yml' is valid!",
+        ));
+}
@@ -1,15 +1,30 @@
 mod tasks {
     use rox::models::{Task, Validate};
+    use std::collections::HashMap;
     fn build_default_task() -> Task {
         Task {
             name: String::from("test_task"),
             command: Some(String::from("some command")),
+            script: None,
             uses: None,
             description: Some(String::from("This is a test task")),
             workdir: Some(String::from("rox/")),
+            shell: None,
             file_path: Some(String::from("some_filepath.yml")),
             values: None,
+            with: None,
             hide: Some(false),
+            depends_on: None,
+            env: None,
+            env_file: None,
+            inputs: None,
+            outputs: None,
+            timeout: None,
+            retries: None,
+            retry_delay: None,
+            allow_failure: None,
+            matrix: None,
+            args: None,
         }
     }
 
@@ -30,9 +45,9 @@ mod tasks {
         assert!(task.values.is_none());
 
         let result = task.validate();
-        assert!(
-            result.is_err_and(|e| e.message == "A Task must implement either 'command' or 'uses'!")
-        );
+        assert!(result.is_err_and(
+            |e| e.message == "A Task must implement one of 'command', 'script' or 'uses'!"
+        ));
     }
 
     #[test]
@@ -46,9 +61,27 @@ mod tasks {
         assert!(task.values.is_none());
 
         let result = task.validate();
-        assert!(
-            result.is_err_and(|e| e.message == "A Task cannot implement both 'command' & 'uses'!")
-        );
+        assert!(result.is_err_and(
+            |e| e.message == "A Task can only implement one of 'command', 'script' & 'uses'!"
+        ));
+    }
+
+    #[test]
+    fn task_has_command_and_script() {
+        let mut task = build_default_task();
+        task.script = Some("echo one\necho two\n".to_owned());
+
+        // Confirm test setup
+        assert!(task.script.is_some());
+        assert!(task.command.is_some());
+
+        let result = task.validate();
+        assert!(result.is_err_and(
+            |e| e.message == "A Task can only implement one of 'command', 'script' & 'uses'!"
+        ));
+
+        task.command = None;
+        assert!(task.validate().is_ok());
     }
 
     #[test]
@@ -63,9 +96,22 @@ mod tasks {
         assert!(task.values.is_none());
 
         let result = task.validate();
-        assert!(result.is_err_and(
-            |e| e.message == "A Task that implements 'uses' must also implement 'values'!"
-        ));
+        assert!(result.is_err_and(|e| e.message
+            == "A Task that implements 'uses' must also implement 'values' or 'with'!"));
+    }
+
+    #[test]
+    fn task_has_values_and_with() {
+        let mut task = build_default_task();
+        task.uses = task.command;
+        task.command = None;
+        task.values = Some(vec!["test".to_owned()]);
+        task.with = Some(HashMap::from([("test".to_owned(), "test".to_owned())]));
+
+        let result = task.validate();
+        assert!(
+            result.is_err_and(|e| e.message == "A Task cannot implement both 'values' & 'with'!")
+        );
     }
 
     #[test]
@@ -85,13 +131,30 @@ mod tasks {
 }
 
 mod templates {
-    use rox::models::{Template, Validate};
+    use rox::models::{Task, Template, TemplateParam, Validate};
+    use std::collections::HashMap;
 
     fn build_default_template() -> Template {
         Template {
             name: String::from("test_task"),
             command: String::from("docker build {path}"),
             symbols: vec!["{path}".to_owned()],
+            params: None,
+            file_path: None,
+        }
+    }
+
+    fn build_named_template() -> Template {
+        Template {
+            name: String::from("test_task"),
+            command: String::from("docker build {path}"),
+            symbols: Vec::new(),
+            params: Some(vec![TemplateParam {
+                name: String::from("path"),
+                default: None,
+                description: None,
+            }]),
+            file_path: None,
         }
     }
 
@@ -111,4 +174,44 @@ mod templates {
             |e| e.message == "A Template's 'symbols' must all exist within its 'command'!"
         ));
     }
+
+    #[test]
+    fn template_symbols_and_params() {
+        let mut template = build_named_template();
+        template.symbols = vec!["{path}".to_owned()];
+
+        let result = template.validate();
+        assert!(result
+            .is_err_and(|e| e.message == "A Template cannot implement both 'symbols' & 'params'!"));
+    }
+
+    #[test]
+    fn template_params_not_in_command() {
+        let mut template = build_named_template();
+        template.command = "some string".to_owned();
+
+        let result = template.validate();
+        assert!(result.is_err_and(
+            |e| e.message == "A Template's 'params' must all exist within its 'command'!"
+        ));
+    }
+
+    #[test]
+    fn template_required_param_unbound() {
+        let template = build_named_template();
+        let mut task = Task {
+            name: String::from("test_task"),
+            uses: Some(template.name.to_owned()),
+            with: Some(HashMap::new()),
+            ..Default::default()
+        };
+
+        let result = template.validate_bindings(&task);
+        assert!(result.is_err_and(
+            |e| e.message == "Template 'test_task' requires a value for its param 'path'!"
+        ));
+
+        task.with = Some(HashMap::from([("path".to_owned(), ".".to_owned())]));
+        assert!(template.validate_bindings(&task).is_ok());
+    }
 }